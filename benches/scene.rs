@@ -0,0 +1,48 @@
+//! 分别开启与关闭光线包追踪渲染示例场景，比较耗时：`cargo bench --bench scene`
+//!
+//! 图像缩小到 WIDTH 像素宽、每像素 SAMPLES 个采样，其余相机参数与命令行默认渲染相同
+
+use std::time::{Duration, Instant};
+
+use ray_tracing::{demo, CancelToken};
+
+/// 图像宽度（像素）
+const WIDTH: usize = 400;
+
+/// 每像素采样数
+const SAMPLES: usize = 8;
+
+fn main() {
+    let scene = demo::random_scene();
+    let world = scene.build().unwrap();
+    let mut camera = demo::camera();
+    camera.image_width = WIDTH;
+    camera.samples_per_pixel = SAMPLES;
+
+    let mut time = |packet_tracing| {
+        camera.packet_tracing = packet_tracing;
+        best_of(|| {
+            camera.render_image(&world, &mut |_| {}, &CancelToken::new());
+        })
+    };
+    let single = time(false);
+    let packets = time(true);
+    println!(
+        "scene {WIDTH}px x {SAMPLES} spp: single rays {:.3} s, packets {:.3} s, speedup {:.2}x",
+        single.as_secs_f64(),
+        packets.as_secs_f64(),
+        single.as_secs_f64() / packets.as_secs_f64()
+    );
+}
+
+/// 重复计时 3 次取最短时间
+fn best_of(mut f: impl FnMut()) -> Duration {
+    (0..3)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
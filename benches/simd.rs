@@ -0,0 +1,115 @@
+//! 比较按 4 通道逐个计算的 AoS `Vec3` 与按 SoA 通道批量计算的 `Vec3Lanes` 在点积、叉积、
+//! 最小值与最大值上的耗时：`cargo bench --bench simd`
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use ray_tracing::{packet::Vec3Lanes, simd::Lanes, Vec3};
+
+/// 每组输入的向量个数
+const COUNT: usize = 1 << 10;
+
+/// 每次计时重复的遍数
+const PASSES: usize = 4000;
+
+/// SoA 的通道数
+const WIDTH: usize = 8;
+
+fn main() {
+    let a: Vec<Vec3> = (0..COUNT)
+        .map(|_| Vec3::random_in_range(-1.0, 1.0))
+        .collect();
+    let b: Vec<Vec3> = (0..COUNT)
+        .map(|_| Vec3::random_in_range(-1.0, 1.0))
+        .collect();
+    let a_lanes = to_lanes(&a);
+    let b_lanes = to_lanes(&b);
+
+    let mut dots = vec![0.0; COUNT];
+    let mut dot_lanes = vec![Lanes::splat(0.0); COUNT / WIDTH];
+    compare(
+        "dot",
+        || {
+            for ((d, a), b) in dots.iter_mut().zip(&a).zip(&b) {
+                *d = a.dot(b);
+            }
+            black_box(&dots);
+        },
+        || {
+            for ((d, a), b) in dot_lanes.iter_mut().zip(&a_lanes).zip(&b_lanes) {
+                *d = a.dot(b);
+            }
+            black_box(&dot_lanes);
+        },
+    );
+
+    let mut out = vec![Vec3::default(); COUNT];
+    let mut out_lanes = a_lanes.clone();
+    let kernels: [(&str, ScalarKernel, LaneKernel); 3] = [
+        ("cross", Vec3::cross, Vec3Lanes::cross),
+        ("min", Vec3::min, Vec3Lanes::min),
+        ("max", Vec3::max, Vec3Lanes::max),
+    ];
+    for (name, scalar, lanes) in kernels {
+        compare(
+            name,
+            || {
+                for ((o, a), b) in out.iter_mut().zip(&a).zip(&b) {
+                    *o = scalar(a, b);
+                }
+                black_box(&out);
+            },
+            || {
+                for ((o, a), b) in out_lanes.iter_mut().zip(&a_lanes).zip(&b_lanes) {
+                    *o = lanes(a, b);
+                }
+                black_box(&out_lanes);
+            },
+        );
+    }
+}
+
+type ScalarKernel = fn(&Vec3, &Vec3) -> Vec3;
+type LaneKernel = fn(&Vec3Lanes<WIDTH>, &Vec3Lanes<WIDTH>) -> Vec3Lanes<WIDTH>;
+
+/// 把向量按 WIDTH 个一组转换为 SoA 布局
+fn to_lanes(v: &[Vec3]) -> Vec<Vec3Lanes<WIDTH>> {
+    v.chunks_exact(WIDTH)
+        .map(|chunk| {
+            let mut lanes = Vec3Lanes::splat(&Vec3::default());
+            for (i, v) in chunk.iter().enumerate() {
+                lanes.set(i, v);
+            }
+            lanes
+        })
+        .collect()
+}
+
+/// 分别计时两种实现并输出每个向量的平均耗时与加速比
+fn compare(name: &str, mut scalar: impl FnMut(), mut lanes: impl FnMut()) {
+    let scalar = best_of(&mut scalar);
+    let lanes = best_of(&mut lanes);
+    let per_vector = |d: Duration| d.as_secs_f64() * 1e9 / (COUNT * PASSES) as f64;
+    println!(
+        "{name:>5}: Vec3 {:.3} ns, Vec3Lanes<{WIDTH}> {:.3} ns, speedup {:.2}x",
+        per_vector(scalar),
+        per_vector(lanes),
+        scalar.as_secs_f64() / lanes.as_secs_f64()
+    );
+}
+
+/// 重复计时 5 次取最短时间
+fn best_of(f: &mut impl FnMut()) -> Duration {
+    (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..PASSES {
+                f();
+            }
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
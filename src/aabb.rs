@@ -0,0 +1,281 @@
+use overload::overload;
+use std::ops;
+
+use crate::{
+    interval::Interval,
+    math::{gamma, Transform},
+    packet::{RayPacket, Vec3Lanes},
+    ray::Ray,
+    simd::{Lanes, Mask},
+    vec3::{Point3, Real, Vec3},
+};
+
+/// 轴对齐包围盒，由三个坐标轴上的区间构成
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Aabb::EMPTY
+    }
+}
+
+impl Aabb {
+    /// 不包含任何点的包围盒，作为合并的初始值
+    pub const EMPTY: Aabb = Aabb {
+        x: Interval::EMPTY,
+        y: Interval::EMPTY,
+        z: Interval::EMPTY,
+    };
+
+    /// 包含整个空间的包围盒
+    pub const UNIVERSE: Aabb = Aabb {
+        x: Interval::UNIVERSE,
+        y: Interval::UNIVERSE,
+        z: Interval::UNIVERSE,
+    };
+
+    pub fn new(x: Interval, y: Interval, z: Interval) -> Self {
+        Self { x, y, z }
+    }
+
+    /// 以两点为对角的包围盒
+    pub fn from_points(a: &Point3, b: &Point3) -> Self {
+        Self::new(
+            Interval::new(a.x.min(b.x), a.x.max(b.x)),
+            Interval::new(a.y.min(b.y), a.y.max(b.y)),
+            Interval::new(a.z.min(b.z), a.z.max(b.z)),
+        )
+    }
+
+    /// 第 n 个坐标轴（0: x, 1: y, 2: z）上的区间，其他 n panic
+    pub fn axis(&self, n: usize) -> &Interval {
+        match n {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("axis index out of range: {n}"),
+        }
+    }
+
+    /// 跨度最大的坐标轴
+    pub fn longest_axis(&self) -> usize {
+        let (x, y, z) = (self.x.size(), self.y.size(), self.z.size());
+        if x > y && x > z {
+            0
+        } else if y > z {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty() || self.y.is_empty() || self.z.is_empty()
+    }
+
+    /// 是否为非空的有限包围盒
+    pub fn is_bounded(&self) -> bool {
+        !self.is_empty()
+            && [self.x, self.y, self.z]
+                .iter()
+                .all(|i| i.min.is_finite() && i.max.is_finite())
+    }
+
+    pub fn min(&self) -> Point3 {
+        Point3::new(self.x.min, self.y.min, self.z.min)
+    }
+
+    pub fn max(&self) -> Point3 {
+        Point3::new(self.x.max, self.y.max, self.z.max)
+    }
+
+    /// 同时包含两个包围盒的最小包围盒
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self::new(
+            self.x.union(&other.x),
+            self.y.union(&other.y),
+            self.z.union(&other.z),
+        )
+    }
+
+    /// 扩展到包含点 p
+    pub fn union_point(&self, p: &Point3) -> Self {
+        self.union(&Self::from_points(p, p))
+    }
+
+    /// 经过 transform 变换后的包围盒，取八个角点变换后的包围盒
+    pub fn transform(&self, transform: &Transform) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+        if !self.is_bounded() {
+            return Self::UNIVERSE; // 无界的包围盒变换后仍视为无界
+        }
+        let (min, max) = (self.min(), self.max());
+        (0..8).fold(Self::EMPTY, |bbox, corner| {
+            let p = Point3::new(
+                if corner & 1 == 0 { min.x } else { max.x },
+                if corner & 2 == 0 { min.y } else { max.y },
+                if corner & 4 == 0 { min.z } else { max.z },
+            );
+            bbox.union_point(&transform.point(&p))
+        })
+    }
+
+    /// 两个包围盒是否相交
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.x.overlaps(&other.x) && self.y.overlaps(&other.y) && self.z.overlaps(&other.z)
+    }
+
+    /// 中心点
+    pub fn centroid(&self) -> Point3 {
+        Point3::new(
+            (self.x.min + self.x.max) / 2.0,
+            (self.y.min + self.y.max) / 2.0,
+            (self.z.min + self.z.max) / 2.0,
+        )
+    }
+
+    /// 表面积，空包围盒为 0
+    pub fn surface_area(&self) -> Real {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let (x, y, z) = (self.x.size(), self.y.size(), self.z.size());
+        2.0 * (x * y + y * z + z * x)
+    }
+
+    /// 光线与包围盒的 slab 求交，返回重叠的光线参数区间
+    pub fn hit(&self, r: &Ray, ray_t: Interval) -> Option<Interval> {
+        let inv_direction = Vec3::new(
+            1.0 / r.direction.x,
+            1.0 / r.direction.y,
+            1.0 / r.direction.z,
+        );
+        self.hit_inv(&r.origin, &inv_direction, ray_t)
+    }
+
+    /// 使用预先计算的方向倒数做 slab 求交，便于遍历时复用
+    #[inline]
+    pub fn hit_inv(
+        &self,
+        origin: &Point3,
+        inv_direction: &Vec3,
+        ray_t: Interval,
+    ) -> Option<Interval> {
+        let (mut t_min, mut t_max) = (ray_t.min, ray_t.max);
+        for axis in 0..3 {
+            let interval = self.axis(axis);
+            let (o, inv) = match axis {
+                0 => (origin.x, inv_direction.x),
+                1 => (origin.y, inv_direction.y),
+                _ => (origin.z, inv_direction.z),
+            };
+            let mut t0 = (interval.min - o) * inv;
+            let mut t1 = (interval.max - o) * inv;
+            if t0 > t1 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t1 *= 1.0 + 2.0 * gamma(3); // 保守地放大远端，避免误差漏掉擦边的交点
+            if t0 > t_min {
+                t_min = t0;
+            }
+            if t1 < t_max {
+                t_max = t1;
+            }
+            if t_min > t_max {
+                return None;
+            }
+        }
+        Some(Interval::new(t_min, t_max))
+    }
+
+    /// 光线包的 slab 求交，返回与包围盒相交的活跃光线
+    #[inline]
+    pub fn hit_packet<const N: usize>(
+        &self,
+        packet: &RayPacket<N>,
+        inv_direction: &Vec3Lanes<N>,
+    ) -> Mask<N> {
+        let mut t_min = Lanes::splat(packet.t_min);
+        let mut t_max = packet.t_max;
+        let slabs = [
+            (&self.x, packet.origin.x, inv_direction.x),
+            (&self.y, packet.origin.y, inv_direction.y),
+            (&self.z, packet.origin.z, inv_direction.z),
+        ];
+        for (interval, o, inv) in slabs {
+            let t0 = (Lanes::splat(interval.min) - o) * inv;
+            let t1 = (Lanes::splat(interval.max) - o) * inv;
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1) * Lanes::splat(1.0 + 2.0 * gamma(3)));
+        }
+        packet.active.and(t_min.le(t_max))
+    }
+}
+
+// 平移
+overload!((a: ?Aabb) + (b: ?Vec3) -> Aabb {
+    Aabb::new(a.x + b.x, a.y + b.y, a.z + b.z)
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::RayKind;
+
+    fn unit_box() -> Aabb {
+        Aabb::from_points(&Point3::new(0.0, 0.0, 0.0), &Point3::new(1.0, 2.0, 3.0))
+    }
+
+    #[test]
+    fn slab_test_finds_entry_and_exit() {
+        let bbox = unit_box();
+        let r = Ray::new(
+            Point3::new(0.5, 1.0, -1.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            RayKind::Camera,
+        );
+        let t = bbox.hit(&r, Interval::new(0.0, Real::INFINITY)).unwrap();
+        assert_eq!(t.min, 1.0);
+        assert!((0.0..1e-5).contains(&(t.max - 4.0)), "{t:?}");
+        // 与坐标轴平行、在某个 slab 之外的光线，方向倒数为无穷大
+        let miss = Ray::new(
+            Point3::new(2.0, 1.0, -1.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            RayKind::Camera,
+        );
+        assert_eq!(bbox.hit(&miss, Interval::new(0.0, Real::INFINITY)), None);
+        // 包围盒在光线区间之外
+        assert_eq!(bbox.hit(&r, Interval::new(0.0, 0.5)), None);
+    }
+
+    #[test]
+    fn union_centroid_and_area() {
+        let bbox = unit_box().union(&Aabb::from_points(
+            &Point3::new(-1.0, 0.0, 0.0),
+            &Point3::new(0.0, 1.0, 1.0),
+        ));
+        assert_eq!(bbox.min(), Point3::new(-1.0, 0.0, 0.0));
+        assert_eq!(bbox.max(), Point3::new(1.0, 2.0, 3.0));
+        assert_eq!(bbox.centroid(), Point3::new(0.0, 1.0, 1.5));
+        assert_eq!(
+            bbox.surface_area(),
+            2.0 * (2.0 * 2.0 + 2.0 * 3.0 + 3.0 * 2.0)
+        );
+        assert_eq!(Aabb::EMPTY.surface_area(), 0.0);
+        assert_eq!(Aabb::EMPTY.union(&bbox), bbox);
+        assert_eq!(bbox.longest_axis(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "axis index out of range")]
+    fn axis_past_z_panics() {
+        unit_box().axis(3);
+    }
+}
@@ -0,0 +1,535 @@
+use std::{
+    fs,
+    io::{self, BufWriter},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    image::{invalid_data, Image},
+    math::Transform,
+    progress::CancelToken,
+    rng,
+    scene::SceneFile,
+    vec3::{Real, Vec3},
+};
+
+/// 关键帧之间的插值方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    Bezier,     // 三次 Bezier，控制点由关键帧的 in_handle / out_handle 给出
+    CatmullRom, // 经过各关键帧的平滑曲线
+}
+
+/// 关键帧，值的各分量分别插值；驱动单个数值的字段时只有一个分量
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub frame: Real,
+    pub value: Vec<Real>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_handle: Option<Vec<Real>>, // Bezier 进入控制点，缺省时取到前一关键帧的 1/3 处
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub out_handle: Option<Vec<Real>>, // Bezier 离开控制点，缺省时取到后一关键帧的 1/3 处
+}
+
+impl Keyframe {
+    pub fn new(frame: Real, value: &[Real]) -> Self {
+        Self {
+            frame,
+            value: value.to_vec(),
+            in_handle: None,
+            out_handle: None,
+        }
+    }
+}
+
+/// 关键帧轨道，关键帧按帧号递增排列；第一帧之前与最后一帧之后保持端点的值
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    pub keys: Vec<Keyframe>,
+}
+
+impl Track {
+    /// 检查关键帧非空、按帧号递增且各值的分量数相同
+    fn validate(&self) -> Result<(), String> {
+        let Some(first) = self.keys.first() else {
+            return Err("track has no keyframes".to_string());
+        };
+        let n = first.value.len();
+        for pair in self.keys.windows(2) {
+            if pair[1].frame <= pair[0].frame {
+                return Err(format!(
+                    "keyframes at {} and {} are not in increasing order",
+                    pair[0].frame, pair[1].frame
+                ));
+            }
+        }
+        let components = self.keys.iter().flat_map(|key| {
+            [
+                Some(&key.value),
+                key.in_handle.as_ref(),
+                key.out_handle.as_ref(),
+            ]
+            .into_iter()
+            .flatten()
+        });
+        for value in components {
+            if value.len() != n {
+                return Err(format!(
+                    "keyframe values have {} and {} components",
+                    n,
+                    value.len()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 在帧 frame 处取值
+    pub fn sample(&self, frame: Real) -> Vec<Real> {
+        let keys = &self.keys;
+        let i = keys.partition_point(|key| key.frame <= frame); // 第一个晚于 frame 的关键帧
+        if i == 0 {
+            return keys[0].value.clone();
+        }
+        if i == keys.len() {
+            return keys[i - 1].value.clone();
+        }
+
+        let (k1, k2) = (&keys[i - 1], &keys[i]);
+        let dt = k2.frame - k1.frame;
+        let u = (frame - k1.frame) / dt;
+        let (p1, p2) = (&k1.value, &k2.value);
+        match self.interpolation {
+            Interpolation::Linear => zip(p1, p2, |a, b| a + (b - a) * u),
+            Interpolation::Bezier => {
+                let c1 = k1
+                    .out_handle
+                    .clone()
+                    .unwrap_or_else(|| zip(p1, p2, |a, b| a + (b - a) / 3.0));
+                let c2 = k2
+                    .in_handle
+                    .clone()
+                    .unwrap_or_else(|| zip(p1, p2, |a, b| b - (b - a) / 3.0));
+                let v = 1.0 - u;
+                let w = [v * v * v, 3.0 * v * v * u, 3.0 * v * u * u, u * u * u];
+                (0..p1.len())
+                    .map(|c| w[0] * p1[c] + w[1] * c1[c] + w[2] * c2[c] + w[3] * p2[c])
+                    .collect()
+            }
+            Interpolation::CatmullRom => {
+                // 以相邻关键帧的差商为切线（每帧的变化量）的三次 Hermite 曲线
+                let m1 = self.slope(i - 1);
+                let m2 = self.slope(i);
+                let (u2, u3) = (u * u, u * u * u);
+                let h = [
+                    2.0 * u3 - 3.0 * u2 + 1.0,
+                    u3 - 2.0 * u2 + u,
+                    -2.0 * u3 + 3.0 * u2,
+                    u3 - u2,
+                ];
+                (0..p1.len())
+                    .map(|c| h[0] * p1[c] + h[1] * dt * m1[c] + h[2] * p2[c] + h[3] * dt * m2[c])
+                    .collect()
+            }
+        }
+    }
+
+    /// 第 i 个关键帧处的切线，端点取单侧差商
+    fn slope(&self, i: usize) -> Vec<Real> {
+        let keys = &self.keys;
+        let a = &keys[i.saturating_sub(1)];
+        let b = &keys[(i + 1).min(keys.len() - 1)];
+        let dt = b.frame - a.frame;
+        zip(&a.value, &b.value, |a, b| {
+            if dt > 0.0 {
+                (b - a) / dt
+            } else {
+                0.0
+            }
+        })
+    }
+}
+
+fn zip(a: &[Real], b: &[Real], f: impl Fn(Real, Real) -> Real) -> Vec<Real> {
+    a.iter().zip(b).map(|(&a, &b)| f(a, b)).collect()
+}
+
+/// 节点变换的分量
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransformComponent {
+    Translation, // 平移 [x, y, z]
+    Rotation,    // 依次绕 x、y、z 轴的旋转角（度）
+    Scale,       // 缩放 [x, y, z]
+}
+
+/// 动画通道驱动的目标
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Target {
+    /// 相机字段，如 lookfrom、lookat、vfov、focus_dist
+    Camera(String),
+    /// 节点变换的一个分量；动画变换作用在节点原有变换之后（在父节点空间中）
+    Transform {
+        node: String,
+        component: TransformComponent,
+    },
+    /// 节点序列化结果中以 / 分隔的字段路径，如 object/radius 或 object/material/fuzz；
+    /// 资源库中的材质与纹理在节点中只写出名称，须用 Material 或 Texture 驱动
+    Property { node: String, path: String },
+    /// 资源库中材质的字段路径，如 fuzz 或 albedo/albedo；使用该材质的物体一同变化
+    Material { name: String, path: String },
+    /// 资源库中纹理的字段路径，如 even/albedo；使用该纹理的材质一同变化
+    Texture { name: String, path: String },
+}
+
+/// 动画通道：一条关键帧轨道及其目标
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Channel {
+    pub target: Target,
+    pub track: Track,
+}
+
+/// 关键帧动画，帧号从 start 到 end（含）
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Animation {
+    pub start: i64,
+    pub end: i64,
+    pub channels: Vec<Channel>,
+}
+
+impl SceneFile {
+    /// 在帧 frame 处求值动画，返回不含动画的场景；没有动画时返回当前场景的副本
+    ///
+    /// 各通道按序列化后的字段修改场景，未知的字段或节点、分量数不符的值都会报错
+    pub fn at_frame(&self, frame: Real) -> io::Result<SceneFile> {
+        let mut value = serde_json::to_value(self)?;
+        value.as_object_mut().unwrap().remove("animation");
+        let channels = self.animation.as_ref().map_or(&[][..], |a| &a.channels);
+
+        // 同一节点的变换分量合并为一个变换
+        let mut transforms: Vec<(String, [Option<Vec<Real>>; 3])> = Vec::new();
+        for channel in channels.iter() {
+            channel
+                .track
+                .validate()
+                .map_err(|e| invalid_data(format!("{:?}: {e}", channel.target)))?;
+            let sample = channel.track.sample(frame);
+            match &channel.target {
+                Target::Camera(field) => {
+                    let camera = &mut value["camera"];
+                    set(camera, field, &sample)
+                        .map_err(|e| invalid_data(format!("camera: {e}")))?;
+                }
+                Target::Transform { node, component } => {
+                    let i = match transforms.iter().position(|(name, _)| name == node) {
+                        Some(i) => i,
+                        None => {
+                            transforms.push((node.clone(), Default::default()));
+                            transforms.len() - 1
+                        }
+                    };
+                    transforms[i].1[*component as usize] = Some(sample);
+                }
+                Target::Property { node, path } => {
+                    let node_value = find_node(&mut value["scene"]["root"], node)?;
+                    set(node_value, path, &sample)
+                        .map_err(|e| invalid_data(format!("node {node}: {e}")))?;
+                }
+                Target::Material { name, path } => {
+                    let material = find_item(&mut value["scene"]["materials"], "material", name)?;
+                    set(material, path, &sample)
+                        .map_err(|e| invalid_data(format!("material {name}: {e}")))?;
+                }
+                Target::Texture { name, path } => {
+                    let texture = find_item(&mut value["scene"]["textures"], "texture", name)?;
+                    set(texture, path, &sample)
+                        .map_err(|e| invalid_data(format!("texture {name}: {e}")))?;
+                }
+            }
+        }
+
+        for (node, [translation, rotation, scale]) in transforms {
+            let vector = |v: Option<Vec<Real>>, default: Real| -> io::Result<Vec3> {
+                match v.as_deref() {
+                    None => Ok(Vec3::new(default, default, default)),
+                    Some(&[x, y, z]) => Ok(Vec3::new(x, y, z)),
+                    Some(v) => Err(invalid_data(format!(
+                        "node {node}: transform components need 3 values, got {}",
+                        v.len()
+                    ))),
+                }
+            };
+            let t = vector(translation, 0.0)?;
+            let r = vector(rotation, 0.0)?;
+            let s = vector(scale, 1.0)?;
+            if s.x == 0.0 || s.y == 0.0 || s.z == 0.0 {
+                return Err(invalid_data(format!("node {node}: zero scale")));
+            }
+            let animated = Transform::translate(&t)
+                * Transform::rotate(&Vec3::new(0.0, 0.0, 1.0), r.z.to_radians())
+                * Transform::rotate(&Vec3::new(0.0, 1.0, 0.0), r.y.to_radians())
+                * Transform::rotate(&Vec3::new(1.0, 0.0, 0.0), r.x.to_radians())
+                * Transform::scale(&s);
+
+            let node_value = find_node(&mut value["scene"]["root"], &node)?;
+            let base: Transform = match node_value.get("transform") {
+                Some(transform) => serde_json::from_value(transform.clone())?,
+                None => Transform::default(),
+            };
+            node_value["transform"] = serde_json::to_value(animated * base)?;
+        }
+
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// 渲染动画的帧 frames，第 n 帧写入把 pattern 中连续的 # 替换为补零帧号的文件，
+    /// 扩展名为 .bmp 时写 BMP，否则写 PPM
+    ///
+    /// 已存在的帧直接跳过，因此中断后重新运行只渲染缺少的帧；每帧先写入临时文件再改名，
+    /// 不会留下不完整的图像。cancel 被取消时在当前帧完成后返回
+    ///
+    /// 各帧的随机种子由相机的 seed 与帧号混合而成，噪点随帧变化而不会固定在画面上
+    pub fn render_frames(
+        &self,
+        frames: RangeInclusive<i64>,
+        pattern: &str,
+        cancel: &CancelToken,
+    ) -> io::Result<()> {
+        let count = frames.clone().count();
+        for (n, frame) in frames.enumerate() {
+            if cancel.is_cancelled() {
+                break;
+            }
+            let path = frame_path(pattern, frame)?;
+            if path.exists() {
+                eprintln!(
+                    "\rFrame {frame} ({}/{count}): {} exists, skipping",
+                    n + 1,
+                    path.display()
+                );
+                continue;
+            }
+
+            let SceneFile {
+                mut camera, scene, ..
+            } = self.at_frame(frame as Real)?;
+            camera.seed = rng::mix_seed(&[camera.seed, frame as u64]);
+            let world = scene.build()?;
+            camera.auto_focus(&scene, &world)?;
+            let image = camera.render_image(
+                &world,
+                &mut |progress| eprint!("\rFrame {frame} ({}/{count}): {progress} ", n + 1),
+                cancel,
+            );
+            if cancel.is_cancelled() {
+                break; // 不保存未完成的帧
+            }
+            write_frame(&image, &path)?;
+            eprintln!(
+                "\rFrame {frame} ({}/{count}): wrote {}",
+                n + 1,
+                path.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// 把 pattern 中第一段连续的 # 替换为补零到同样宽度的帧号，负帧号在补零后的数字前加负号
+pub fn frame_path(pattern: &str, frame: i64) -> io::Result<PathBuf> {
+    let Some(start) = pattern.find('#') else {
+        return Err(invalid_data(format!(
+            "frame pattern {pattern:?} has no # placeholder"
+        )));
+    };
+    let width = pattern[start..].chars().take_while(|&c| c == '#').count();
+    let sign = if frame < 0 { "-" } else { "" };
+    Ok(PathBuf::from(format!(
+        "{}{sign}{:0width$}{}",
+        &pattern[..start],
+        frame.unsigned_abs(),
+        &pattern[start + width..]
+    )))
+}
+
+/// 写入临时文件后改名为 path
+fn write_frame(image: &Image, path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".partial");
+    {
+        let mut out = BufWriter::new(fs::File::create(&tmp)?);
+        let bmp = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("bmp"));
+        if bmp {
+            image.write_bmp(&mut out)?;
+        } else {
+            image.write_ppm(&mut out)?;
+        }
+    }
+    fs::rename(&tmp, path)
+}
+
+/// 深度优先查找序列化后的节点树中名为 name 的节点
+fn find_node<'a>(node: &'a mut Value, name: &str) -> io::Result<&'a mut Value> {
+    fn find<'a>(node: &'a mut Value, name: &str) -> Option<&'a mut Value> {
+        if node.get("name").and_then(Value::as_str) == Some(name) {
+            return Some(node);
+        }
+        node.get_mut("children")?
+            .as_array_mut()?
+            .iter_mut()
+            .find_map(|child| find(child, name))
+    }
+    find(node, name).ok_or_else(|| invalid_data(format!("no node named {name:?}")))
+}
+
+/// 序列化后的资源库 library 中名为 name 的资源，kind 为资源类别
+fn find_item<'a>(library: &'a mut Value, kind: &str, name: &str) -> io::Result<&'a mut Value> {
+    library
+        .get_mut(name)
+        .ok_or_else(|| invalid_data(format!("no {kind} named {name:?} in the library")))
+}
+
+/// 把 root 下以 / 分隔的已有字段 path 设为 sample，字段为数时 sample 只能有一个分量，
+/// 为数组时分量数须与数组长度相同
+fn set(root: &mut Value, path: &str, sample: &[Real]) -> Result<(), String> {
+    let mut value = root;
+    for key in path.split('/').filter(|key| !key.is_empty()) {
+        value = match value {
+            Value::Object(map) => map.get_mut(key),
+            Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get_mut(i)),
+            Value::String(name) => {
+                return Err(format!(
+                    "no field {path:?}: {name:?} refers to a library resource, \
+                     animate it with a Material or Texture target"
+                ))
+            }
+            _ => None,
+        }
+        .ok_or_else(|| format!("no field {path:?}"))?;
+    }
+    let number = |x: Real| match serde_json::to_value(x) {
+        Ok(number @ Value::Number(_)) => Ok(number),
+        _ => Err(format!("{path:?}: value is not finite")),
+    };
+    match (&mut *value, sample) {
+        (Value::Number(_), [x]) => *value = number(*x)?,
+        (Value::Array(items), _) if items.len() == sample.len() => {
+            for (item, &x) in items.iter_mut().zip(sample) {
+                *item = number(x)?;
+            }
+        }
+        _ => {
+            return Err(format!(
+                "{path:?}: cannot set {value} to {} component(s)",
+                sample.len()
+            ))
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(interpolation: Interpolation, keys: &[(Real, Real)]) -> Track {
+        Track {
+            interpolation,
+            keys: keys
+                .iter()
+                .map(|&(frame, value)| Keyframe::new(frame, &[value]))
+                .collect(),
+        }
+    }
+
+    fn assert_close(a: Real, b: Real) {
+        assert!((a - b).abs() < 1e-5, "{a} != {b}");
+    }
+
+    const ALL: [Interpolation; 3] = [
+        Interpolation::Linear,
+        Interpolation::Bezier,
+        Interpolation::CatmullRom,
+    ];
+
+    #[test]
+    fn endpoints_are_held_outside_the_keys() {
+        for interpolation in ALL {
+            let t = track(interpolation, &[(10.0, 1.0), (20.0, 5.0), (30.0, 2.0)]);
+            assert_eq!(t.sample(-100.0), [1.0]);
+            assert_eq!(t.sample(10.0), [1.0]);
+            assert_eq!(t.sample(30.0), [2.0]);
+            assert_eq!(t.sample(1000.0), [2.0]);
+        }
+    }
+
+    #[test]
+    fn linear_and_default_bezier_interpolate_evenly() {
+        for interpolation in [Interpolation::Linear, Interpolation::Bezier] {
+            let t = track(interpolation, &[(0.0, 0.0), (4.0, 8.0)]);
+            assert_close(t.sample(1.0)[0], 2.0);
+            assert_close(t.sample(3.0)[0], 6.0);
+        }
+    }
+
+    #[test]
+    fn bezier_follows_handles() {
+        let mut t = track(Interpolation::Bezier, &[(0.0, 0.0), (1.0, 0.0)]);
+        t.keys[0].out_handle = Some(vec![4.0]);
+        t.keys[1].in_handle = Some(vec![4.0]);
+        // 中点处的权重为 1/8、3/8、3/8、1/8
+        assert_close(t.sample(0.5)[0], 3.0);
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_keys() {
+        let keys = [(0.0, 1.0), (2.0, 4.0), (5.0, -1.0), (6.0, 3.0)];
+        let t = track(Interpolation::CatmullRom, &keys);
+        for (frame, value) in keys {
+            assert_close(t.sample(frame)[0], value);
+        }
+        // 关键帧之间平滑变化，不等于线性插值
+        assert!((t.sample(1.0)[0] - 2.5).abs() > 1e-3);
+        // 等距且共线的关键帧给出直线
+        let line = track(
+            Interpolation::CatmullRom,
+            &[(0.0, 0.0), (1.0, 2.0), (2.0, 4.0)],
+        );
+        assert_close(line.sample(0.25)[0], 0.5);
+        assert_close(line.sample(1.5)[0], 3.0);
+    }
+
+    #[test]
+    fn validate_rejects_bad_tracks() {
+        assert!(track(Interpolation::Linear, &[]).validate().is_err());
+        assert!(track(Interpolation::Linear, &[(1.0, 0.0), (1.0, 1.0)])
+            .validate()
+            .is_err());
+        let mut t = track(Interpolation::Linear, &[(0.0, 0.0), (1.0, 1.0)]);
+        assert!(t.validate().is_ok());
+        t.keys[1].value = vec![1.0, 2.0];
+        assert!(t.validate().is_err());
+    }
+
+    #[test]
+    fn frame_path_pads_frame_numbers() {
+        let path = |pattern, frame| frame_path(pattern, frame).unwrap();
+        assert_eq!(path("out/f_####.ppm", 7), PathBuf::from("out/f_0007.ppm"));
+        assert_eq!(path("f_##.ppm", 12345), PathBuf::from("f_12345.ppm"));
+        assert_eq!(path("f_####.ppm", -5), PathBuf::from("f_-0005.ppm"));
+        assert_eq!(path("f_#_#.ppm", 3), PathBuf::from("f_3_#.ppm"));
+        assert!(frame_path("frame.ppm", 1).is_err());
+    }
+}
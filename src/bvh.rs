@@ -0,0 +1,526 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aabb::Aabb,
+    hittable::{Hittable, HittableList, SurfaceInteraction},
+    interval::Interval,
+    packet::{
+        hit_each, hit_lanes, occluded_each, PacketRecords, RayPacket, DIVERGENCE_THRESHOLD,
+        PACKET_WIDTH,
+    },
+    ray::Ray,
+    simd::Mask,
+    stats,
+    vec3::{Point3, Real, Vec3},
+};
+
+/// 叶节点最多包含的物体数
+const MAX_LEAF_SIZE: usize = 4;
+/// 表面积启发式（SAH）划分时的分桶数
+const SAH_BUCKETS: usize = 12;
+/// 遍历一个子节点相对于求交一个物体的代价
+const TRAVERSAL_COST: Real = 0.125;
+/// 遍历栈深度
+const STACK_SIZE: usize = 64;
+/// 树的最大深度（根节点深度为 0），保证遍历栈不会溢出
+const MAX_DEPTH: usize = STACK_SIZE - 2;
+
+enum BvhNodeKind {
+    Leaf { first: usize, count: usize }, // 物体在 objects 中的范围
+    Interior { second: usize, axis: usize }, // 第一个子节点紧随其后，axis 为划分轴
+}
+
+struct BvhNode {
+    bbox: Aabb,
+    kind: BvhNodeKind,
+}
+
+/// 构建时的物体信息
+struct BuildItem {
+    object: Rc<dyn Hittable>,
+    bbox: Aabb,
+    centroid: Point3,
+}
+
+/// 层次包围盒，节点按深度优先顺序存放在数组中；只序列化物体，读取时重新构建节点
+///
+/// 包围盒无界的物体（如未实现 bounding_box 的物体）不进入树，放在 objects 末尾，
+/// 每条光线遍历完树后再逐个与之求交
+#[derive(Serialize, Deserialize)]
+#[serde(from = "HittableList")]
+pub struct Bvh {
+    #[serde(skip_serializing)]
+    nodes: Vec<BvhNode>,
+    objects: Vec<Rc<dyn Hittable>>, // 树中的物体按叶节点顺序排列，之后是无界的物体
+    #[serde(skip_serializing)]
+    bounded: usize, // 树中的物体数
+}
+
+impl Bvh {
+    /// 按表面积启发式为列表中的物体构建层次包围盒
+    pub fn new(list: HittableList) -> Self {
+        let (mut items, unbounded): (Vec<BuildItem>, Vec<BuildItem>) = list
+            .objects
+            .into_iter()
+            .map(|object| {
+                let bbox = object.bounding_box();
+                BuildItem {
+                    object,
+                    bbox,
+                    centroid: bbox.centroid(),
+                }
+            })
+            .partition(|item| item.bbox.is_bounded());
+
+        let mut nodes = Vec::with_capacity(2 * items.len());
+        if !items.is_empty() {
+            build(&mut items, 0, 0, &mut nodes);
+        }
+        Self {
+            nodes,
+            bounded: items.len(),
+            objects: items
+                .into_iter()
+                .chain(unbounded)
+                .map(|item| item.object)
+                .collect(),
+        }
+    }
+
+    /// 不在树中的无界物体
+    fn unbounded(&self) -> &[Rc<dyn Hittable>] {
+        &self.objects[self.bounded..]
+    }
+
+    /// 从节点 root 开始遍历单条光线
+    fn traverse(&self, root: usize, r: &Ray, ray_t: Interval) -> Option<SurfaceInteraction<'_>> {
+        let inv_direction = Vec3::new(
+            1.0 / r.direction.x,
+            1.0 / r.direction.y,
+            1.0 / r.direction.z,
+        );
+        let direction_negative = [
+            inv_direction.x < 0.0,
+            inv_direction.y < 0.0,
+            inv_direction.z < 0.0,
+        ];
+
+        let mut closest = None;
+        let mut closest_so_far = ray_t.max;
+        let mut stack = [0; STACK_SIZE];
+        let mut stack_len = 1;
+        stack[0] = root;
+        while stack_len > 0 {
+            stack_len -= 1;
+            let index = stack[stack_len];
+            let node = &self.nodes[index];
+            stats::record(|s| s.bvh_node_visits += 1);
+            let node_t = Interval::new(ray_t.min, closest_so_far);
+            if node
+                .bbox
+                .hit_inv(&r.origin, &inv_direction, node_t)
+                .is_none()
+            {
+                continue;
+            }
+            match node.kind {
+                BvhNodeKind::Leaf { first, count } => {
+                    for object in &self.objects[first..first + count] {
+                        if let Some(si) = object.hit(r, Interval::new(ray_t.min, closest_so_far)) {
+                            closest_so_far = si.t;
+                            closest = Some(si);
+                        }
+                    }
+                }
+                BvhNodeKind::Interior { second, axis } => {
+                    // 先访问靠近光线起点的子节点
+                    let (near, far) = if direction_negative[axis] {
+                        (second, index + 1)
+                    } else {
+                        (index + 1, second)
+                    };
+                    stack[stack_len] = far;
+                    stack[stack_len + 1] = near;
+                    stack_len += 2;
+                }
+            }
+        }
+        closest
+    }
+
+    /// 从节点 root 开始遍历单条光线，找到任一交点即返回
+    fn traverse_any(&self, root: usize, r: &Ray, ray_t: Interval) -> bool {
+        let inv_direction = Vec3::new(
+            1.0 / r.direction.x,
+            1.0 / r.direction.y,
+            1.0 / r.direction.z,
+        );
+
+        let mut stack = [0; STACK_SIZE];
+        let mut stack_len = 1;
+        stack[0] = root;
+        while stack_len > 0 {
+            stack_len -= 1;
+            let index = stack[stack_len];
+            let node = &self.nodes[index];
+            stats::record(|s| s.bvh_node_visits += 1);
+            if node
+                .bbox
+                .hit_inv(&r.origin, &inv_direction, ray_t)
+                .is_none()
+            {
+                continue;
+            }
+            match node.kind {
+                BvhNodeKind::Leaf { first, count } => {
+                    let objects = &self.objects[first..first + count];
+                    if objects.iter().any(|object| object.occluded(r, ray_t)) {
+                        return true;
+                    }
+                }
+                BvhNodeKind::Interior { second, .. } => {
+                    stack[stack_len] = second;
+                    stack[stack_len + 1] = index + 1;
+                    stack_len += 2;
+                }
+            }
+        }
+        false
+    }
+}
+
+impl From<HittableList> for Bvh {
+    fn from(list: HittableList) -> Self {
+        Bvh::new(list)
+    }
+}
+
+#[typetag::serde]
+impl Hittable for Bvh {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<SurfaceInteraction<'_>> {
+        let mut closest = if self.nodes.is_empty() {
+            None
+        } else {
+            self.traverse(0, r, ray_t)
+        };
+        for object in self.unbounded() {
+            let t_max = closest.map_or(ray_t.max, |si| si.t);
+            if let Some(si) = object.hit(r, Interval::new(ray_t.min, t_max)) {
+                closest = Some(si);
+            }
+        }
+        closest
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        (!self.nodes.is_empty() && self.traverse_any(0, r, ray_t))
+            || self
+                .unbounded()
+                .iter()
+                .any(|object| object.occluded(r, ray_t))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        if !self.unbounded().is_empty() {
+            return Aabb::UNIVERSE;
+        }
+        self.nodes.first().map_or(Aabb::EMPTY, |node| node.bbox)
+    }
+
+    fn contains_instance(&self) -> bool {
+        self.objects.iter().any(|object| object.contains_instance())
+    }
+
+    /// 整个光线包一起遍历；进入某节点的光线发散后，改为逐条遍历该子树
+    fn hit_packet<'a>(
+        &'a self,
+        packet: &mut RayPacket<PACKET_WIDTH>,
+        recs: &mut PacketRecords<'a>,
+    ) -> Mask<PACKET_WIDTH> {
+        if self.nodes.is_empty() || packet.diverged() {
+            return hit_each(self, packet, recs);
+        }
+
+        let inv_direction = packet.inv_direction();
+        let active = packet.active;
+
+        let mut hits = Mask([false; PACKET_WIDTH]);
+        let mut stack = [0; STACK_SIZE];
+        let mut stack_len = 1;
+        while stack_len > 0 {
+            stack_len -= 1;
+            let index = stack[stack_len];
+            let node = &self.nodes[index];
+            stats::record(|s| s.bvh_node_visits += 1);
+            let lanes = node.bbox.hit_packet(packet, &inv_direction);
+            if !lanes.any() {
+                continue;
+            }
+            if lanes.count() <= DIVERGENCE_THRESHOLD {
+                hits = hits.or(hit_lanes(lanes, packet, recs, |r, ray_t| {
+                    self.traverse(index, r, ray_t)
+                }));
+                continue;
+            }
+            match node.kind {
+                BvhNodeKind::Leaf { first, count } => {
+                    packet.active = lanes;
+                    for object in &self.objects[first..first + count] {
+                        hits = hits.or(object.hit_packet(packet, recs));
+                    }
+                    packet.active = active;
+                }
+                BvhNodeKind::Interior { second, axis } => {
+                    // 按第一条相交光线的方向决定子节点顺序
+                    let lane = lanes.0.iter().position(|&b| b).unwrap_or(0);
+                    let negative = inv_direction.get(lane)[axis] < 0.0;
+                    let (near, far) = if negative {
+                        (second, index + 1)
+                    } else {
+                        (index + 1, second)
+                    };
+                    stack[stack_len] = far;
+                    stack[stack_len + 1] = near;
+                    stack_len += 2;
+                }
+            }
+        }
+        for object in self.unbounded() {
+            hits = hits.or(object.hit_packet(packet, recs));
+        }
+        hits
+    }
+
+    fn occluded_packet(&self, packet: &RayPacket<PACKET_WIDTH>) -> Mask<PACKET_WIDTH> {
+        if self.nodes.is_empty() || packet.diverged() {
+            return occluded_each(self, packet);
+        }
+
+        let inv_direction = packet.inv_direction();
+
+        // 已被遮挡的光线不再参与后续遍历
+        let mut remaining = *packet;
+        let mut occluded = Mask([false; PACKET_WIDTH]);
+        let mut stack = [0; STACK_SIZE];
+        let mut stack_len = 1;
+        while stack_len > 0 && remaining.active.any() {
+            stack_len -= 1;
+            let index = stack[stack_len];
+            let node = &self.nodes[index];
+            stats::record(|s| s.bvh_node_visits += 1);
+            let lanes = node.bbox.hit_packet(&remaining, &inv_direction);
+            if !lanes.any() {
+                continue;
+            }
+            if lanes.count() <= DIVERGENCE_THRESHOLD {
+                for (i, o) in occluded.0.iter_mut().enumerate() {
+                    if lanes.0[i] && self.traverse_any(index, &packet.ray(i), packet.interval(i)) {
+                        *o = true;
+                    }
+                }
+            } else {
+                match node.kind {
+                    BvhNodeKind::Leaf { first, count } => {
+                        let mut leaf = remaining;
+                        leaf.active = lanes;
+                        for object in &self.objects[first..first + count] {
+                            occluded = occluded.or(object.occluded_packet(&leaf));
+                            leaf.active = lanes.and(!occluded);
+                            if !leaf.active.any() {
+                                break;
+                            }
+                        }
+                    }
+                    BvhNodeKind::Interior { second, .. } => {
+                        stack[stack_len] = second;
+                        stack[stack_len + 1] = index + 1;
+                        stack_len += 2;
+                    }
+                }
+            }
+            remaining.active = packet.active.and(!occluded);
+        }
+        for object in self.unbounded() {
+            if !remaining.active.any() {
+                break;
+            }
+            occluded = occluded.or(object.occluded_packet(&remaining));
+            remaining.active = packet.active.and(!occluded);
+        }
+        occluded
+    }
+}
+
+/// 递归构建 items 对应的子树，items 在 objects 中从 offset 开始，子树根节点深度为 depth，
+/// 返回子树根节点下标
+///
+/// 按 SAH 划分可能使树很深，深度加上剩余物体数的对数达到 MAX_DEPTH 时改为按质心中位数对半划分，
+/// 因此整棵树的深度不超过 MAX_DEPTH
+fn build(items: &mut [BuildItem], offset: usize, depth: usize, nodes: &mut Vec<BvhNode>) -> usize {
+    debug_assert!(depth <= MAX_DEPTH, "BVH depth {depth} exceeds {MAX_DEPTH}");
+    let bbox = items
+        .iter()
+        .fold(Aabb::EMPTY, |bbox, item| bbox.union(&item.bbox));
+    let index = nodes.len();
+    let leaf = BvhNode {
+        bbox,
+        kind: BvhNodeKind::Leaf {
+            first: offset,
+            count: items.len(),
+        },
+    };
+
+    let centroid_bounds = items.iter().fold(Aabb::EMPTY, |bounds, item| {
+        bounds.union_point(&item.centroid)
+    });
+    let axis = centroid_bounds.longest_axis();
+    let (lo, hi) = (
+        centroid_bounds.axis(axis).min,
+        centroid_bounds.axis(axis).max,
+    );
+    if items.len() <= 1 || hi <= lo {
+        nodes.push(leaf);
+        return index;
+    }
+    let levels_needed = (usize::BITS - (items.len() - 1).leading_zeros()) as usize;
+    if depth + levels_needed >= MAX_DEPTH {
+        if items.len() <= MAX_LEAF_SIZE {
+            nodes.push(leaf);
+            return index;
+        }
+        let mid = items.len() / 2;
+        items.select_nth_unstable_by(mid, |a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+        return build_interior(items, mid, offset, depth, axis, leaf, nodes);
+    }
+
+    // 按质心把物体分到若干桶中，选出代价最小的划分
+    let bucket_of = |item: &BuildItem| {
+        let b = ((item.centroid[axis] - lo) / (hi - lo) * SAH_BUCKETS as Real) as usize;
+        b.min(SAH_BUCKETS - 1)
+    };
+    let mut counts = [0usize; SAH_BUCKETS];
+    let mut bounds = [Aabb::EMPTY; SAH_BUCKETS];
+    for item in items.iter() {
+        let b = bucket_of(item);
+        counts[b] += 1;
+        bounds[b] = bounds[b].union(&item.bbox);
+    }
+
+    let area = bbox.surface_area();
+    let (mut best_cost, mut best_split) = (Real::INFINITY, 0);
+    for split in 1..SAH_BUCKETS {
+        let (left, right) =
+            (0..SAH_BUCKETS).fold(((0, Aabb::EMPTY), (0, Aabb::EMPTY)), |(left, right), b| {
+                if b < split {
+                    ((left.0 + counts[b], left.1.union(&bounds[b])), right)
+                } else {
+                    (left, (right.0 + counts[b], right.1.union(&bounds[b])))
+                }
+            });
+        if left.0 == 0 || right.0 == 0 {
+            continue;
+        }
+        let cost = TRAVERSAL_COST
+            + (left.0 as Real * left.1.surface_area() + right.0 as Real * right.1.surface_area())
+                / area;
+        if cost < best_cost {
+            (best_cost, best_split) = (cost, split);
+        }
+    }
+
+    if best_split == 0 || (items.len() <= MAX_LEAF_SIZE && best_cost >= items.len() as Real) {
+        nodes.push(leaf);
+        return index;
+    }
+
+    // 按所选划分重新排列物体
+    let mut mid = 0;
+    for i in 0..items.len() {
+        if bucket_of(&items[i]) < best_split {
+            items.swap(i, mid);
+            mid += 1;
+        }
+    }
+
+    build_interior(items, mid, offset, depth, axis, leaf, nodes)
+}
+
+/// 以 items 的前 mid 个与其余物体分别构建两棵子树，node 为本节点（先作为占位加入），返回其下标
+fn build_interior(
+    items: &mut [BuildItem],
+    mid: usize,
+    offset: usize,
+    depth: usize,
+    axis: usize,
+    node: BvhNode,
+    nodes: &mut Vec<BvhNode>,
+) -> usize {
+    let index = nodes.len();
+    nodes.push(node); // 占位，子树建好后改为内部节点
+    let (left, right) = items.split_at_mut(mid);
+    build(left, offset, depth + 1, nodes);
+    let second = build(right, offset + mid, depth + 1, nodes);
+    nodes[index].kind = BvhNodeKind::Interior { second, axis };
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::{Lambertian, Material},
+        ray::RayKind,
+        sphere::Sphere,
+        vec3::Color,
+    };
+
+    /// 以 spheres 中的球分别构建 BVH 与线性列表
+    fn build(spheres: &[(Point3, Real)]) -> (Bvh, HittableList) {
+        let material: Rc<dyn Material> = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mut list = HittableList::new();
+        let mut tree = HittableList::new();
+        for &(center, radius) in spheres {
+            let sphere: Rc<dyn Hittable> = Rc::new(Sphere::new(center, radius, material.clone()));
+            list.add(sphere.clone());
+            tree.add(sphere);
+        }
+        (Bvh::new(tree), list)
+    }
+
+    /// 随机光线与 BVH 和线性列表求交的结果相同
+    fn assert_matches_list(bvh: &Bvh, list: &HittableList) {
+        let ray_t = Interval::new(0.001, Real::INFINITY);
+        for _ in 0..500 {
+            let origin = Point3(Vec3::random_in_range(-12.0, 12.0));
+            let r = Ray::new(origin, Vec3::random_unit_vector(), RayKind::Camera);
+            let expected = list.hit(&r, ray_t).map(|si| si.t);
+            assert_eq!(bvh.hit(&r, ray_t).map(|si| si.t), expected);
+            assert_eq!(bvh.occluded(&r, ray_t), expected.is_some());
+        }
+    }
+
+    #[test]
+    fn bvh_hits_match_linear_list() {
+        let spheres: Vec<_> = (0..200)
+            .map(|_| {
+                let center = Point3(Vec3::random_in_range(-10.0, 10.0));
+                (center, 0.1 + Vec3::random().x)
+            })
+            .collect();
+        let (bvh, list) = build(&spheres);
+        assert_eq!(bvh.bounding_box(), list.bounding_box());
+        assert_matches_list(&bvh, &list);
+    }
+
+    #[test]
+    fn coincident_objects_stay_within_the_stack() {
+        // 质心相同的物体无法按 SAH 划分，树的深度仍须受 MAX_DEPTH 限制
+        let spheres: Vec<_> = (0..5000)
+            .map(|i| (Point3::new(0.0, 0.0, 0.0), 1.0 + i as Real * 1e-3))
+            .collect();
+        let (bvh, list) = build(&spheres);
+        assert_matches_list(&bvh, &list);
+    }
+}
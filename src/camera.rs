@@ -1,34 +1,102 @@
-use std::{f64::INFINITY, rc::Rc};
+use std::{io, ops::Range, time::Instant};
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    environment::{Environment, Gradient},
     hittable::{HitRecord, Hittable},
+    image::{invalid_data, Image},
     interval::Interval,
-    material::Lambertian,
-    ray::Ray,
-    vec3::Vec3,
+    light::LightSet,
+    math::Onb,
+    packet::{PacketRecords, RayPacket, PACKET_WIDTH},
+    progress::{CancelToken, Progress, Tile},
+    projection::{Projection, Stereo, ThinLens, View},
+    ray::{Ray, RayKind},
+    rng,
+    scene::Scene,
+    stats::{self, PhaseTimer},
+    vec3::{Color, Point3, Real, Vec3},
 };
-use rand::Rng;
+
+/// 开启俄罗斯轮盘赌时，至少散射这么多次之后才可能终止路径
+const ROULETTE_MIN_BOUNCES: usize = 3;
+
+/// 分块渲染的图块边长（像素），取 PACKET_WIDTH 的整数倍
+const TILE_SIZE: usize = 32;
+
+/// 对焦峰值显示中标记清晰区域的颜色
+const PEAKING_COLOR: Color = Color(Vec3 {
+    x: 0.0,
+    y: 1.0,
+    z: 0.0,
+});
+
+/// 对焦峰值显示中标记颜色的最大混合比例
+const PEAKING_OPACITY: Real = 0.7;
+
+/// 自动对焦方式：渲染前求出对焦点并把 focus_dist 设为它在视线方向上的深度
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AutoFocus {
+    Center,         // 画面中心光线的第一个交点，未击中物体时保持原焦距
+    Object(String), // 场景中名为该名称的节点：指向其包围盒中心的光线与它的第一个交点
+    Point(Point3),  // 世界空间中的点
+}
+
+/// 裁剪窗口的范围，原点在图像左上角
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CropWindow {
+    Pixels(Tile), // 像素矩形
+    Normalized {
+        x0: Real,
+        y0: Real,
+        x1: Real,
+        y1: Real,
+    }, // 按图像宽高归一化的 [0, 1] 范围
+}
+
+/// 裁剪渲染：只追踪窗口内的像素，投影与整幅图像相同
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Crop {
+    pub window: CropWindow,
+    pub full_frame: bool, // 输出整幅图像并把窗口外留黑，否则只输出窗口内的图像
+}
+
+/// 相机，序列化时省略由 initialize 计算的字段，缺少的字段取默认值
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Camera {
-    pub aspect_ratio: f64,        // 长宽比
-    pub image_width: usize,       // 图像宽度
-    pub samples_per_pixel: usize, // 每像素采样次数
-    pub max_depth: usize,         // 最大深度
-    pub vfov: f64,                // 视角
-    pub lookfrom: Vec3,           // 观察点
-    pub lookat: Vec3,             // 观察目标
-    pub vup: Vec3,                // 观察向上
-    pub defocus_angle: f64,       // 散焦角度
-    pub focus_dist: f64,          // 焦距
-    image_height: usize,          // 图像高度
-    center: Vec3,                 // 相机中心
-    pixel00_loc: Vec3,            // 像素00位置
-    pixel_delta_u: Vec3,          // 水平像素间隔
-    pixel_delta_v: Vec3,          // 垂直像素间隔
-    u: Vec3,                      // 水平向量
-    v: Vec3,                      // 垂直向量
-    w: Vec3,                      // 观察向量
-    defocus_disk_u: Vec3,         // 散焦盘 u
-    defocus_disk_v: Vec3,         // 散焦盘 v
+    pub aspect_ratio: Real,                // 长宽比
+    pub image_width: usize,                // 图像宽度
+    pub samples_per_pixel: usize,          // 每像素采样次数
+    pub max_depth: usize,                  // 最大深度
+    pub vfov: Real,                        // 视角
+    pub lookfrom: Point3,                  // 观察点
+    pub lookat: Point3,                    // 观察目标
+    pub vup: Vec3,                         // 观察向上
+    pub focus_dist: Real,                  // 焦距
+    pub f_number: Real,                    // 光圈 f 值
+    pub shutter_speed: Real,               // 快门时间（秒）
+    pub iso: Real,                         // 感光度
+    pub exposure_compensation: Real,       // 曝光补偿（EV）
+    pub sensor_height: Real,               // 底片高度（世界单位）
+    pub projection: Box<dyn Projection>,   // 投影模型
+    pub stereo: Option<Stereo>,            // 立体渲染
+    pub environment: Box<dyn Environment>, // 环境光
+    pub packet_tracing: bool, // 主光线与阴影光线按光线包求交（耗时比较见 benches/scene.rs）
+    pub russian_roulette: bool, // 按俄罗斯轮盘赌提前终止贡献小的路径
+    pub seed: u64,            // 随机种子，与眼睛、光线包位置和起始采样序号决定随机序列
+    pub crop: Option<Crop>,   // 裁剪窗口
+    pub autofocus: Option<AutoFocus>, // 自动对焦，设置后渲染前按它求出 focus_dist
+    pub focus_peaking: Option<Real>, // 对焦峰值显示：弥散圆直径小于该值（像素）的区域标为绿色
+    #[serde(skip)]
+    image_height: usize, // 图像高度
+    #[serde(skip)]
+    view: View, // 视图参数
+    #[serde(skip)]
+    crop_rect: Tile, // 要渲染的像素区域
+    #[serde(skip)]
+    object_focus: Option<(String, Point3)>, // auto_focus 求出的对焦物体及其上的对焦点
 }
 
 impl Default for Camera {
@@ -39,163 +107,708 @@ impl Default for Camera {
             samples_per_pixel: 10,
             max_depth: 10,
             vfov: 90.0,
-            lookfrom: Vec3::new(0.0, 0.0, -1.0),
-            lookat: Vec3::new(0.0, 0.0, 0.0),
+            lookfrom: Point3::new(0.0, 0.0, -1.0),
+            lookat: Point3::new(0.0, 0.0, 0.0),
             vup: Vec3::new(0.0, 1.0, 0.0),
-            defocus_angle: 0.0,
             focus_dist: 10.0,
+            f_number: 16.0,
+            shutter_speed: 1.0 / 100.0,
+            iso: 100.0,
+            exposure_compensation: 0.0,
+            sensor_height: 0.024,
+            projection: Box::new(ThinLens::default()),
+            stereo: None,
+            environment: Box::new(Gradient),
+            packet_tracing: true,
+            russian_roulette: false,
+            seed: 0,
+            crop: None,
+            autofocus: None,
+            focus_peaking: None,
             image_height: Default::default(),
-            center: Vec3::default(),
-            pixel00_loc: Vec3::default(),
-            pixel_delta_u: Vec3::default(),
-            pixel_delta_v: Vec3::default(),
-            u: Vec3::default(),
-            v: Vec3::default(),
-            w: Vec3::default(),
-            defocus_disk_u: Vec3::default(),
-            defocus_disk_v: Vec3::default(),
+            view: View::default(),
+            crop_rect: Tile::default(),
+            object_focus: None,
         }
     }
 }
 
 impl Camera {
-    pub fn render(&mut self, world: &dyn Hittable) {
+    /// 渲染并把 PPM 图像写到标准输出，进度与剩余时间输出到标准错误
+    pub fn render(&mut self, world: &dyn Hittable) -> io::Result<()> {
+        let image = self.render_image(
+            world,
+            &mut |progress| eprintln!("\r{progress} "),
+            &CancelToken::new(),
+        );
+        let _timer = PhaseTimer::start("write");
+        image.write_ppm(&mut io::stdout().lock())?;
+        eprintln!("\rDone.                 ");
+        Ok(())
+    }
+
+    /// 按图块渲染，返回经过曝光的线性颜色图像；立体渲染时各眼图像自上而下拼接，
+    /// 设置了裁剪窗口时只渲染窗口内的像素，图像大小见 output_size
+    ///
+    /// 设置了 autofocus 时先对焦；对焦于物体时需先调用 auto_focus 按名称找到物体，
+    /// 否则保持原焦距并给出警告
+    ///
+    /// 每完成一个图块调用一次 on_progress；cancel 被取消时在当前图块完成后返回，
+    /// 未渲染的图块为黑色
+    pub fn render_image(
+        &mut self,
+        world: &dyn Hittable,
+        on_progress: &mut dyn FnMut(&Progress),
+        cancel: &CancelToken,
+    ) -> Image {
+        let _timer = PhaseTimer::start("render");
+        self.focus(world);
+        let tiles = self.tiles();
+        let exposure = self.exposure();
+        let (width, height) = self.output_size();
+        let mut image = Image::new(width, height);
+        let samples_total = tiles
+            .iter()
+            .map(|(_, tile)| (tile.width * tile.height * self.samples_per_pixel) as u64)
+            .sum();
+        let mut samples_done = 0;
+        let start = Instant::now();
+        for (tiles_done, &(k, tile)) in tiles.iter().enumerate() {
+            if cancel.is_cancelled() {
+                break;
+            }
+            let sums = self.render_tile(world, k, tile, 0..self.samples_per_pixel);
+            let tile = self.output_tile(k, tile);
+            image.add_tile(&tile, &sums, exposure / self.samples_per_pixel as Real);
+
+            samples_done += (tile.width * tile.height * self.samples_per_pixel) as u64;
+            on_progress(&Progress::new(
+                tile,
+                tiles_done + 1,
+                tiles.len(),
+                samples_done,
+                samples_total,
+                start.elapsed(),
+            ));
+        }
+        stats::flush();
+        if !cancel.is_cancelled() {
+            self.overlay_focus_peaking(world, &mut image);
+        }
+        image
+    }
+
+    /// 按 autofocus 设置 focus_dist，scene 用于按名称查找对焦物体，求出的对焦点供之后的渲染使用；
+    /// 对焦物体不存在时返回错误
+    pub fn auto_focus(&mut self, scene: &Scene, world: &dyn Hittable) -> io::Result<()> {
+        if let Some(AutoFocus::Object(name)) = &self.autofocus {
+            let object = scene
+                .build_node(name)?
+                .ok_or_else(|| invalid_data(format!("no object named {name:?} to focus on")))?;
+            let centroid = object.bounding_box().centroid();
+            let r = Ray::new(self.lookfrom, centroid - self.lookfrom, RayKind::Camera);
+            let point = first_hit(&object, &r).unwrap_or(centroid);
+            self.object_focus = Some((name.clone(), point));
+        }
+        self.focus(world);
+        Ok(())
+    }
+
+    /// 按 autofocus 设置 focus_dist；对焦物体尚未由 auto_focus 求出时保持原焦距并给出警告
+    fn focus(&mut self, world: &dyn Hittable) {
+        let Some(autofocus) = self.autofocus.clone() else {
+            return;
+        };
         self.initialize();
+        let target = match autofocus {
+            AutoFocus::Center => self
+                .pinhole_ray(0.5, 0.5)
+                .and_then(|r| first_hit(world, &r)),
+            AutoFocus::Object(name) => match &self.object_focus {
+                Some((object, point)) if *object == name => Some(*point),
+                _ => {
+                    eprintln!(
+                        "\rWarning: autofocus on object {name:?} needs Camera::auto_focus with the scene, keeping focus_dist"
+                    );
+                    None
+                }
+            },
+            AutoFocus::Point(p) => Some(p),
+        };
+        if let Some(depth) = target.map(|p| self.depth(&p)).filter(|&z| z > 0.0) {
+            self.focus_dist = depth;
+        }
+    }
 
-        println!("P3\n{} {}\n255\n", self.image_width, self.image_height);
+    /// 薄透镜模型下第 eye 只眼睛的像素 (i, j) 中心所见表面的弥散圆直径（像素），
+    /// 未击中物体，或投影的景深不只取决于深度（如倾斜的焦平面）时返回 None；需先调用 tiles 或渲染
+    pub fn circle_of_confusion(
+        &mut self,
+        world: &dyn Hittable,
+        eye: usize,
+        i: usize,
+        j: usize,
+    ) -> Option<Real> {
+        if !self.projection.defocus_by_depth() {
+            return None;
+        }
+        self.view.eye_offset = match &self.stereo {
+            Some(stereo) => stereo.eye_offsets()[eye],
+            None => 0.0,
+        };
+        let s = (i as Real + 0.5) / self.image_width as Real;
+        let t = (j as Real + 0.5) / self.image_height as Real;
+        let z = self.depth(&first_hit(world, &self.pinhole_ray(s, t)?)?);
+        if z <= 0.0 {
+            return None;
+        }
+        // 入瞳直径 2r 在焦平面上形成的光斑，按焦平面上画面的高度换算为像素
+        let frame_height = 2.0 * self.view.focus_dist * (self.vfov.to_radians() / 2.0).tan();
+        let blur = 2.0 * self.view.lens_radius * (z - self.view.focus_dist).abs() / z;
+        Some(blur / frame_height * self.image_height as Real)
+    }
 
-        for j in 0..self.image_height {
-            eprintln!("\rScanlines remaining: {} ", self.image_height - j);
-            for i in 0..self.image_width {
-                let mut pixel_color = Vec3::new(0.0, 0.0, 0.0);
-                for _ in 0..self.samples_per_pixel {
-                    let r = self.get_ray(i, j);
-                    pixel_color += self.ray_color(&r, world, self.max_depth);
+    /// 设置了 focus_peaking 时，把图像中清晰的像素按清晰程度向标记颜色混合；
+    /// 投影不是焦平面与画面平行的薄透镜时无法估算弥散圆，给出警告后跳过
+    pub fn overlay_focus_peaking(&mut self, world: &dyn Hittable, image: &mut Image) {
+        let Some(threshold) = self.focus_peaking else {
+            return;
+        };
+        if !self.projection.defocus_by_depth() {
+            eprintln!("\rWarning: focus peaking needs a thin lens without tilt, skipped");
+            return;
+        }
+        let _timer = PhaseTimer::start("focus peaking");
+        for (eye, tile) in self.tiles() {
+            let out = self.output_tile(eye, tile);
+            for dy in 0..tile.height {
+                for dx in 0..tile.width {
+                    let Some(coc) = self.circle_of_confusion(world, eye, tile.x + dx, tile.y + dy)
+                    else {
+                        continue;
+                    };
+                    let weight = PEAKING_OPACITY * (1.0 - coc / threshold).clamp(0.0, 1.0);
+                    let pixel = &mut image.pixels[(out.y + dy) * image.width + out.x + dx];
+                    *pixel = *pixel * (1.0 - weight) + PEAKING_COLOR * weight;
+                }
+            }
+        }
+    }
+
+    /// 图像高度（单只眼睛），在调用 tiles 或渲染之后有效
+    pub fn image_height(&self) -> usize {
+        self.image_height
+    }
+
+    /// 立体渲染的眼睛数，非立体渲染时为 1
+    pub fn eye_count(&self) -> usize {
+        self.stereo
+            .as_ref()
+            .map_or(1, |stereo| stereo.eye_offsets().len())
+    }
+
+    /// 初始化相机并按渲染顺序列出 (眼睛序号, 图块)，图块坐标为该眼图像中的坐标；
+    /// 图块按整幅图像的网格划分，设置了裁剪窗口时只保留与窗口相交的图块并裁剪到窗口内
+    pub fn tiles(&mut self) -> Vec<(usize, Tile)> {
+        self.initialize();
+        let rect = self.crop_rect;
+        let (right, bottom) = (rect.x + rect.width, rect.y + rect.height);
+        let mut tiles = Vec::new();
+        for k in 0..self.eye_count() {
+            for y0 in (rect.y / TILE_SIZE * TILE_SIZE..bottom).step_by(TILE_SIZE) {
+                for x0 in (rect.x / TILE_SIZE * TILE_SIZE..right).step_by(TILE_SIZE) {
+                    let (x, y) = (x0.max(rect.x), y0.max(rect.y));
+                    let tile = Tile {
+                        x,
+                        y,
+                        width: (x0 + TILE_SIZE).min(right) - x,
+                        height: (y0 + TILE_SIZE).min(bottom) - y,
+                    };
+                    tiles.push((k, tile));
                 }
-                write_color(pixel_color, self.samples_per_pixel);
             }
         }
-        eprintln!("\rDone.                 ")
+        tiles
+    }
+
+    /// 输出图像的 (宽, 高)，立体渲染时为各眼图像自上而下拼接后的高度；在调用 tiles 或渲染之后有效
+    pub fn output_size(&self) -> (usize, usize) {
+        let (width, height) = match self.crop {
+            Some(crop) if !crop.full_frame => (self.crop_rect.width, self.crop_rect.height),
+            _ => (self.image_width, self.image_height),
+        };
+        (width, height * self.eye_count())
+    }
+
+    /// 第 eye 只眼睛的图块 tile 在输出图像中的位置
+    pub fn output_tile(&self, eye: usize, tile: Tile) -> Tile {
+        let (x, y, height) = match self.crop {
+            Some(crop) if !crop.full_frame => (
+                tile.x - self.crop_rect.x,
+                tile.y - self.crop_rect.y,
+                self.crop_rect.height,
+            ),
+            _ => (tile.x, tile.y, self.image_height),
+        };
+        Tile {
+            x,
+            y: eye * height + y,
+            ..tile
+        }
+    }
+
+    /// 渲染第 eye 只眼睛的图块 tile 中序号在 samples 范围内的采样，按行返回各像素未经曝光的颜色之和
+    ///
+    /// 同一行相邻的 PACKET_WIDTH 个像素组成一个光线包，光线包对齐到整幅图像的网格，
+    /// 其随机序列由 seed、眼睛、光线包位置与 samples.start 决定。因此同一任务无论在哪个进程中
+    /// 渲染结果都相同，裁剪窗口内的像素也与完整渲染时相同
+    pub fn render_tile(
+        &mut self,
+        world: &dyn Hittable,
+        eye: usize,
+        tile: Tile,
+        samples: Range<usize>,
+    ) -> Vec<Color> {
+        self.initialize();
+        self.view.eye_offset = match &self.stereo {
+            Some(stereo) => stereo.eye_offsets()[eye],
+            None => 0.0,
+        };
+        let right = tile.x + tile.width;
+        let mut sums = Vec::with_capacity(tile.width * tile.height);
+        for j in tile.y..tile.y + tile.height {
+            // 光线包可能伸出图块，图块外的像素照常追踪后丢弃，以保持随机序列不变
+            for i0 in (tile.x / PACKET_WIDTH * PACKET_WIDTH..right).step_by(PACKET_WIDTH) {
+                rng::seed(rng::mix_seed(&[
+                    self.seed,
+                    eye as u64,
+                    i0 as u64,
+                    j as u64,
+                    samples.start as u64,
+                ]));
+                let n = PACKET_WIDTH.min(self.image_width - i0);
+                let pixel_colors = self.sample_pixels(world, i0, j, n, samples.len());
+                sums.extend_from_slice(&pixel_colors[tile.x.max(i0) - i0..right.min(i0 + n) - i0]);
+            }
+        }
+        sums
+    }
+
+    /// 对第 j 行从 i0 开始的 n 个像素各采样 samples 次，返回各像素的颜色之和
+    fn sample_pixels(
+        &self,
+        world: &dyn Hittable,
+        i0: usize,
+        j: usize,
+        n: usize,
+        samples: usize,
+    ) -> [Color; PACKET_WIDTH] {
+        let mut pixel_colors = [Color::default(); PACKET_WIDTH];
+        for _ in 0..samples {
+            let rays: [Option<Ray>; PACKET_WIDTH] =
+                std::array::from_fn(|k| if k < n { self.get_ray(i0 + k, j) } else { None });
+            let colors = if self.packet_tracing {
+                self.trace_packet(&rays, world)
+            } else {
+                rays.map(|r| {
+                    r.map_or(Color::default(), |r| {
+                        self.ray_color(&r, world, self.max_depth)
+                    })
+                })
+            };
+            for (pixel_color, color) in pixel_colors.iter_mut().zip(colors) {
+                *pixel_color += color;
+            }
+        }
+        pixel_colors
     }
 
     fn initialize(&mut self) {
-        self.image_height = (self.image_width as f64 / self.aspect_ratio) as usize; // 图像高度
+        self.image_height = (self.image_width as Real / self.aspect_ratio) as usize; // 图像高度
         self.image_height = if self.image_height < 1 {
             1
         } else {
             self.image_height
         }; // 确保至少为 1
 
-        self.center = self.lookfrom.clone(); // 相机中心
+        self.view = View {
+            center: self.lookfrom, // 相机中心
+            basis: Onb::with_up(&(self.lookfrom - self.lookat), &self.vup),
+            aspect: self.image_width as Real / self.image_height as Real, // 实际宽高比
+            vfov: self.vfov,
+            focus_dist: self.focus_dist,
+            lens_radius: self.focal_length() / (2.0 * self.f_number), // 入瞳半径
+            eye_offset: 0.0,
+        };
 
-        let theta = self.vfov.to_radians(); // 视角
-        let h = (theta / 2.0).tan(); // 高度 h=1/2*tan(θ)
+        let (w, h) = (self.image_width, self.image_height);
+        self.crop_rect = match self.crop.map(|crop| crop.window) {
+            None => Tile {
+                x: 0,
+                y: 0,
+                width: w,
+                height: h,
+            },
+            Some(CropWindow::Pixels(rect)) => {
+                let (x, y) = (rect.x.min(w - 1), rect.y.min(h - 1));
+                Tile {
+                    x,
+                    y,
+                    width: rect.width.clamp(1, w - x),
+                    height: rect.height.clamp(1, h - y),
+                }
+            }
+            Some(CropWindow::Normalized { x0, y0, x1, y1 }) => {
+                let floor = |t: Real, n: usize| ((t * n as Real).floor().max(0.0) as usize).min(n);
+                let ceil = |t: Real, n: usize| ((t * n as Real).ceil().max(0.0) as usize).min(n);
+                let (x, y) = (floor(x0, w).min(w - 1), floor(y0, h).min(h - 1));
+                let (x1, y1) = (ceil(x1, w), ceil(y1, h));
+                Tile {
+                    x,
+                    y,
+                    width: x1.saturating_sub(x).max(1),
+                    height: y1.saturating_sub(y).max(1),
+                }
+            }
+        }; // 至少包含一个像素
+    }
 
-        let viewport_height = 2.0 * h * self.focus_dist; // 视口高度
-        let viewport_width = viewport_height * (self.image_width as f64 / self.image_height as f64); // 视口宽度
+    /// 由底片高度和视角推算的镜头焦距
+    pub fn focal_length(&self) -> Real {
+        self.sensor_height / 2.0 / (self.vfov.to_radians() / 2.0).tan()
+    }
 
-        self.w = (&self.lookfrom - &self.lookat).unit_vector(); // 观察向量
-        self.u = self.vup.cross(&self.w).unit_vector(); // 水平向量
-        self.v = self.w.cross(&self.u); // 垂直向量
+    /// ISO 100 下的曝光值
+    pub fn ev100(&self) -> Real {
+        (self.f_number * self.f_number / self.shutter_speed * 100.0 / self.iso).log2()
+    }
 
-        // Calculate the vector3 across the horizontal and down the vertical viewport
-        let viewport_u = &self.u * viewport_width; // 水平视口
-        let viewport_v = -&self.v * viewport_height; // 垂直视口
+    /// 底片曝光系数，以“晴天 16 法则”（f/16、1/100 s、ISO 100）为基准，
+    /// 此时亮度为 1 的天空恰好正常曝光
+    pub fn exposure(&self) -> Real {
+        let daylight_ev100 = (16.0 as Real * 16.0 * 100.0).log2();
+        (daylight_ev100 - self.ev100() + self.exposure_compensation).exp2()
+    }
 
-        // Calculate the horizontal and vertical delta vectors from pixel to pixel
-        self.pixel_delta_u = &viewport_u / self.image_width as f64; // 水平像素间隔
-        self.pixel_delta_v = &viewport_v / self.image_height as f64; // 垂直像素间隔
+    /// 沿视线方向（-w）从相机中心到点 p 的深度，需先初始化
+    fn depth(&self, p: &Point3) -> Real {
+        (*p - self.view.center).dot(&-self.view.basis.w)
+    }
 
-        // Calculate the location of the upper left pixel
-        let viewport_upper_left =
-            &self.center - (&viewport_u + &viewport_v) / 2.0 - (self.focus_dist * &self.w); // 视口左上角
-        self.pixel00_loc = viewport_upper_left + (&self.pixel_delta_u + &self.pixel_delta_v) / 2.0;
-        // 像素00位置
+    /// 不经过透镜孔径、穿过画面上 (s, t) 处的光线，用于对焦与对焦峰值显示
+    fn pinhole_ray(&self, s: Real, t: Real) -> Option<Ray> {
+        let view = View {
+            center: self.view.center,
+            basis: self.view.basis,
+            aspect: self.view.aspect,
+            vfov: self.view.vfov,
+            focus_dist: self.view.focus_dist,
+            lens_radius: 0.0,
+            eye_offset: self.view.eye_offset,
+        };
+        self.projection.generate_ray(&view, s, t)
+    }
 
-        let focus_disk_radius = (self.defocus_angle / 2.0).to_radians().tan() * self.focus_dist;
-        self.defocus_disk_u = &self.u * focus_disk_radius;
-        self.defocus_disk_v = &self.v * focus_disk_radius;
+    /// 在像素 (i, j) 内随机采样一条光线
+    fn get_ray(&self, i: usize, j: usize) -> Option<Ray> {
+        let s = (i as Real + rng::random::<Real>()) / self.image_width as Real;
+        let t = (j as Real + rng::random::<Real>()) / self.image_height as Real;
+        self.projection.generate_ray(&self.view, s, t)
     }
 
-    fn get_ray(&self, i: usize, j: usize) -> Ray {
-        let pixel_center =
-            &self.pixel00_loc + (&self.pixel_delta_u * i as f64) + (&self.pixel_delta_v * j as f64);
-        let pixel_sample = pixel_center + self.pixel_sample_square();
+    /// 光线颜色
+    pub fn ray_color(&self, r: &Ray, world: &dyn Hittable, depth: usize) -> Color {
+        self.trace(r, world, depth, None, LightSet::ALL)
+    }
 
-        let ray_origin = if self.defocus_angle <= 0.0 {
-            self.center.clone()
-        } else {
-            self.defocus_disk_sample()
+    /// 路径追踪，在漫反射表面对环境光做重要性采样并按多重重要性采样（MIS）合并；
+    /// bsdf_pdf 为上一次散射采样到 r 的概率密度，仅当上一次散射可与光源采样合并时给出；
+    /// links 为发出 r 的表面链接的光源，r 击中的光源只有在其中时才计入
+    fn trace(
+        &self,
+        r: &Ray,
+        world: &dyn Hittable,
+        depth: usize,
+        bsdf_pdf: Option<Real>,
+        links: LightSet,
+    ) -> Color {
+        let bounce = self.max_depth.saturating_sub(depth); // r 之前的散射次数
+        if depth == 0 {
+            stats::record(|s| {
+                s.terminations.max_depth += 1;
+                s.add_path(bounce);
+            });
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        stats::record(|s| s.rays.add(r.kind, 1));
+        let rec = &match world.hit(r, Interval::new(0.0, Real::INFINITY)) {
+            Some(si) => si.resolve(r), // 交点
+            None => {
+                stats::record(|s| {
+                    s.terminations.escaped += 1;
+                    s.add_path(bounce + 1);
+                });
+                return self.miss(r, bsdf_pdf, links);
+            }
         };
-        let ray_direction = pixel_sample - &ray_origin;
 
-        Ray::new(ray_origin, ray_direction)
+        let direct = match self.sample_light(r, rec) {
+            Some((wi, contribution)) => {
+                stats::record(|s| s.rays.shadow += 1);
+                let shadow_ray = rec.spawn_ray(wi, RayKind::Shadow);
+                if world.occluded(&shadow_ray, Interval::new(0.0, Real::INFINITY)) {
+                    Color::default()
+                } else {
+                    contribution
+                }
+            }
+            None => Color::default(),
+        };
+        self.emitted(rec, links) + self.shade(r, rec, world, depth, direct)
     }
 
-    fn defocus_disk_sample(&self) -> Vec3 {
-        let p = Vec3::random_in_unit_disk();
-        &self.defocus_disk_u * p.x + &self.defocus_disk_v * p.y + &self.center
+    /// 以光线包追踪一组相机光线：主光线和直接光照的阴影光线成包求交，之后的散射逐条追踪
+    fn trace_packet(
+        &self,
+        rays: &[Option<Ray>; PACKET_WIDTH],
+        world: &dyn Hittable,
+    ) -> [Color; PACKET_WIDTH] {
+        let mut colors = [Color::default(); PACKET_WIDTH];
+        if self.max_depth == 0 {
+            return colors;
+        }
+
+        let mut packet = RayPacket::new(rays, Interval::new(0.0, Real::INFINITY));
+        stats::record(|s| s.rays.camera += packet.active.count() as u64);
+        let mut hits: PacketRecords = [None; PACKET_WIDTH];
+        world.hit_packet(&mut packet, &mut hits);
+        let recs: [Option<HitRecord>; PACKET_WIDTH] =
+            std::array::from_fn(|k| Some(hits[k]?.resolve(rays[k].as_ref()?)));
+
+        // 光源采样，阴影光线同样组成光线包
+        let mut lights: [Option<(Vec3, Color)>; PACKET_WIDTH] = [None; PACKET_WIDTH];
+        for (light, (r, rec)) in lights.iter_mut().zip(rays.iter().zip(recs.iter())) {
+            if let (Some(r), Some(rec)) = (r, rec) {
+                *light = self.sample_light(r, rec);
+            }
+        }
+        let shadow_rays = std::array::from_fn(|k| {
+            let rec = recs[k].as_ref()?;
+            lights[k].map(|(wi, _)| rec.spawn_ray(wi, RayKind::Shadow))
+        });
+        let shadow_packet = RayPacket::new(&shadow_rays, Interval::new(0.0, Real::INFINITY));
+        stats::record(|s| s.rays.shadow += shadow_packet.active.count() as u64);
+        let occluded = world.occluded_packet(&shadow_packet);
+
+        for k in 0..PACKET_WIDTH {
+            let Some(r) = &rays[k] else {
+                continue;
+            };
+            colors[k] = match &recs[k] {
+                Some(rec) => {
+                    let direct = match lights[k] {
+                        Some((_, contribution)) if !occluded.0[k] => contribution,
+                        _ => Color::default(),
+                    };
+                    self.emitted(rec, LightSet::ALL)
+                        + self.shade(r, rec, world, self.max_depth, direct)
+                }
+                None => {
+                    stats::record(|s| {
+                        s.terminations.escaped += 1;
+                        s.add_path(1);
+                    });
+                    self.miss(r, None, LightSet::ALL)
+                }
+            };
+        }
+        colors
     }
 
-    fn pixel_sample_square(&self) -> Vec3 {
-        let px = -0.5 + rand::thread_rng().gen_range(0.0..1.0);
-        let py = -0.5 + rand::thread_rng().gen_range(0.0..1.0);
-        px * &self.pixel_delta_u + py * &self.pixel_delta_v
+    /// 未击中物体时返回环境光，links 不含环境光时为黑色
+    fn miss(&self, r: &Ray, bsdf_pdf: Option<Real>, links: LightSet) -> Color {
+        if !links.intersects(&LightSet::ENVIRONMENT) {
+            return Color::default();
+        }
+        let radiance = self.environment.radiance(&r.direction);
+        match bsdf_pdf {
+            Some(pdf) => radiance * power_heuristic(pdf, self.environment.pdf(&r.direction)),
+            None => radiance,
+        }
     }
 
-    /// 光线颜色
-    pub fn ray_color(&self, r: &Ray, world: &dyn Hittable, depth: usize) -> Vec3 {
-        let rec = &mut HitRecord {
-            p: Vec3::default(),
-            normal: Vec3::default(),
-            t: 0.0,
-            front_face: false,
-            mat: Rc::new(Lambertian::new(Vec3::default())),
-        }; // 交点
-        if depth == 0 {
-            return Vec3::new(0.0, 0.0, 0.0);
-        }
-        if world.hit(r, Interval::new(0.001, INFINITY), rec) {
-            // 如果光线击中物体
-            let mut scattered = Ray::default();
-            let mut attenuation = Vec3::default();
-            if rec.mat.scatter(r, rec, &mut attenuation, &mut scattered) {
-                // 如果材质发生散射
-                return attenuation * self.ray_color(&scattered, world, depth - 1);
+    /// 对环境光做一次光源采样，返回 (入射方向, 未被遮挡时的直接光照)
+    fn sample_light(&self, r: &Ray, rec: &HitRecord) -> Option<(Vec3, Color)> {
+        if !rec.links.intersects(&LightSet::ENVIRONMENT) {
+            return None;
+        }
+        let (wi, light_pdf) = self.environment.sample()?;
+        let (f, pdf) = rec.mat.eval(r, rec, &wi)?;
+        if light_pdf <= 0.0 || f.near_zero() {
+            return None;
+        }
+        let contribution =
+            f * self.environment.radiance(&wi) * power_heuristic(light_pdf, pdf) / light_pdf;
+        Some((wi, contribution))
+    }
+
+    /// 交点处的自发光，发光表面不在 links 中时为黑色
+    fn emitted(&self, rec: &HitRecord, links: LightSet) -> Color {
+        if !links.intersects(&rec.light) {
+            return Color::default();
+        }
+        rec.mat.emitted(rec)
+    }
+
+    /// 在交点处加上直接光照 direct，并按材质继续散射
+    fn shade(
+        &self,
+        r: &Ray,
+        rec: &HitRecord,
+        world: &dyn Hittable,
+        depth: usize,
+        direct: Color,
+    ) -> Color {
+        let mut scattered = Ray::default();
+        let mut attenuation = Color::default();
+        let bounce = self.max_depth.saturating_sub(depth); // r 之前的散射次数
+        if !rec.mat.scatter(r, rec, &mut attenuation, &mut scattered) {
+            stats::record(|s| {
+                s.terminations.absorbed += 1;
+                s.add_path(bounce + 1);
+            });
+            return direct;
+        }
+
+        // 俄罗斯轮盘赌：以衰减的最大分量为继续概率，继续时按概率放大以保持无偏
+        if self.russian_roulette && bounce + 1 >= ROULETTE_MIN_BOUNCES {
+            let survival = attenuation.x.max(attenuation.y).max(attenuation.z).min(1.0);
+            if rng::random::<Real>() >= survival {
+                stats::record(|s| {
+                    s.terminations.roulette += 1;
+                    s.add_path(bounce + 1);
+                });
+                return direct;
             }
-            return Vec3::new(0.0, 0.0, 0.0);
-            // 返回法线颜色
+            attenuation = attenuation / survival;
         }
-        let unit_direction = r.direction.unit_vector();
-        let a = 0.5 * (unit_direction.y + 1.0);
-        (1.0 - a) * Vec3::new(1.0, 1.0, 1.0) + a * Vec3::new(0.5, 0.7, 1.0)
+
+        let pdf = rec
+            .mat
+            .eval(r, rec, &scattered.direction)
+            .map(|(_, pdf)| pdf);
+        direct + attenuation * self.trace(&scattered, world, depth - 1, pdf, rec.links)
     }
 }
 
-pub fn write_color(pixel_color: Vec3, samples_per_pixel: usize) {
-    let (mut r, mut g, mut b) = (pixel_color.x, pixel_color.y, pixel_color.z);
+/// 光线 r 与 world 的第一个交点
+fn first_hit(world: &dyn Hittable, r: &Ray) -> Option<Point3> {
+    world
+        .hit(r, Interval::new(0.0, Real::INFINITY))
+        .map(|si| r.at(si.t))
+}
 
-    let scale = 1.0 / samples_per_pixel as f64;
-    r *= scale;
-    g *= scale;
-    b *= scale;
+/// 多重重要性采样的幂启发式权重
+fn power_heuristic(pdf: Real, other_pdf: Real) -> Real {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b == 0.0 {
+        return 0.0;
+    }
+    a / (a + b)
+}
 
-    r = linear_to_gamma(r);
-    g = linear_to_gamma(g);
-    b = linear_to_gamma(b);
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
 
-    static INTENSITY: Interval = Interval {
-        min: 0.0,
-        max: 0.999,
+    use super::*;
+    use crate::{
+        bvh::Bvh,
+        material::{Lambertian, Metal},
+        scene::{Node, Scene},
+        sphere::Sphere,
     };
 
-    let ir = (256.0 * INTENSITY.clamp(r)) as usize;
-    let ig = (256.0 * INTENSITY.clamp(g)) as usize;
-    let ib = (256.0 * INTENSITY.clamp(b)) as usize;
+    fn world() -> Bvh {
+        let mut scene = Scene::default();
+        let ground = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let metal = Rc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.2));
+        scene.add(Node::object(
+            "ground",
+            Rc::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, ground)),
+        ));
+        scene.add(Node::object(
+            "ball",
+            Rc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, metal)),
+        ));
+        scene.build().unwrap()
+    }
 
-    println!("{ir} {ig} {ib}")
-}
+    fn camera(crop: Option<Crop>) -> Camera {
+        Camera {
+            image_width: 80,
+            samples_per_pixel: 4,
+            max_depth: 6,
+            lookfrom: Point3::new(0.0, 0.5, 1.5),
+            lookat: Point3::new(0.0, 0.0, -1.0),
+            seed: 3,
+            crop,
+            ..Default::default()
+        }
+    }
+
+    fn render(crop: Option<Crop>) -> Image {
+        camera(crop).render_image(&world(), &mut |_| {}, &CancelToken::new())
+    }
+
+    /// 跨越图块与光线包边界的窗口
+    const WINDOW: Tile = Tile {
+        x: 37,
+        y: 9,
+        width: 30,
+        height: 20,
+    };
 
-fn linear_to_gamma(linear_component: f64) -> f64 {
-    linear_component.sqrt()
+    #[test]
+    fn crop_equals_the_same_window_of_the_full_render() {
+        let full = render(None);
+        let crop = render(Some(Crop {
+            window: CropWindow::Pixels(WINDOW),
+            full_frame: false,
+        }));
+        assert_eq!((crop.width, crop.height), (WINDOW.width, WINDOW.height));
+        for y in 0..WINDOW.height {
+            for x in 0..WINDOW.width {
+                assert_eq!(
+                    crop.get(x, y),
+                    full.get(WINDOW.x + x, WINDOW.y + y),
+                    "pixel ({x}, {y})"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn full_frame_crop_leaves_the_rest_black() {
+        let full = render(None);
+        let framed = render(Some(Crop {
+            window: CropWindow::Pixels(WINDOW),
+            full_frame: true,
+        }));
+        assert_eq!((framed.width, framed.height), (full.width, full.height));
+        let inside = |x: usize, y: usize| {
+            (WINDOW.x..WINDOW.x + WINDOW.width).contains(&x)
+                && (WINDOW.y..WINDOW.y + WINDOW.height).contains(&y)
+        };
+        for y in 0..full.height {
+            for x in 0..full.width {
+                let expected = if inside(x, y) {
+                    *full.get(x, y)
+                } else {
+                    Color::default()
+                };
+                assert_eq!(*framed.get(x, y), expected, "pixel ({x}, {y})");
+            }
+        }
+    }
 }
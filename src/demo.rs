@@ -0,0 +1,104 @@
+//! 示例场景：地面上随机分布的小球与三个大球，命令行未给出场景文件时渲染它，基准测试也使用它
+
+use std::rc::Rc;
+
+use crate::{
+    camera::Camera,
+    material::{Dielectric, Lambertian, Material, Metal},
+    scene::{Node, Scene},
+    sphere::Sphere,
+    vec3::{Color, Point3, Real, Vec3},
+};
+
+/// 随机分布小球的示例场景
+pub fn random_scene() -> Scene {
+    let mut scene = Scene::default();
+    let ground_material = scene.materials.add(
+        "ground",
+        Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+    );
+    scene.add(Node::object(
+        "ground",
+        Rc::new(Sphere::new(
+            Point3::new(0.0, -1000.0, 0.0),
+            1000.0,
+            ground_material,
+        )),
+    ));
+
+    let small_spheres = scene.add(Node::group("small_spheres"));
+    for a in -11..11 {
+        for b in -11..11 {
+            let choose_mat = rand::random::<Real>();
+            let center = Point3::new(
+                a as Real + 0.9 * rand::random::<Real>(),
+                0.2,
+                b as Real + 0.9 * rand::random::<Real>(),
+            );
+
+            if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                let sphere_material: Rc<dyn Material> = if choose_mat < 0.8 {
+                    // Diffuse
+                    let albedo = Color(Vec3::random() * Vec3::random());
+                    Rc::new(Lambertian::new(albedo))
+                } else if choose_mat < 0.95 {
+                    // metal
+                    let albedo = Color(Vec3::random_in_range(0.5, 1.0)); // 0.5 ~ 1.0
+                    let fuzz = rand::random::<Real>() * 0.5; // 0 ~ 0.5
+                    Rc::new(Metal::new(albedo, fuzz))
+                } else {
+                    // Glass
+                    Rc::new(Dielectric::new(1.5))
+                };
+                small_spheres.add(Node::object(
+                    &format!("sphere_{a}_{b}"),
+                    Rc::new(Sphere::new(center, 0.2, sphere_material)),
+                ));
+            }
+        }
+    }
+
+    let material1 = scene.materials.add("glass", Rc::new(Dielectric::new(1.5)));
+    scene.add(Node::object(
+        "glass_sphere",
+        Rc::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, material1)),
+    ));
+
+    let material2 = scene
+        .materials
+        .add("brown", Rc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1))));
+    scene.add(Node::object(
+        "diffuse_sphere",
+        Rc::new(Sphere::new(Point3::new(-4.0, 1.0, 0.0), 1.0, material2)),
+    ));
+
+    let material3 = scene.materials.add(
+        "mirror",
+        Rc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0)),
+    );
+    scene.add(Node::object(
+        "metal_sphere",
+        Rc::new(Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, material3)),
+    ));
+
+    scene
+}
+
+/// 示例场景的相机
+pub fn camera() -> Camera {
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 1200;
+    cam.samples_per_pixel = 500;
+    cam.max_depth = 50;
+    cam.vfov = 20.0;
+    cam.lookfrom = Point3::new(13.0, 2.0, 3.0);
+    cam.lookat = Point3::new(0.0, 0.0, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+    cam.focus_dist = 10.0;
+    cam.sensor_height = 0.24; // 场景单位约为分米
+    cam.f_number = 5.6;
+    cam.shutter_speed = 1.0 / 800.0;
+    cam.iso = 100.0;
+    cam
+}
@@ -0,0 +1,292 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    image::{invalid_data, Image},
+    progress::{Progress, Tile},
+    scene::SceneFile,
+    vec3::{Color, Real},
+};
+
+/// 工作进程连接协调端的最多尝试次数，允许先于协调端启动
+const CONNECT_ATTEMPTS: usize = 50;
+
+/// 两次连接尝试的间隔
+const CONNECT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 等待工作进程一次读写的最长时间，包括首项任务前读取场景与构建 BVH 的时间；
+/// 超时的工作进程被断开，其任务重新排队
+const WORKER_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// 一项渲染任务：某只眼睛的一个图块中一段序号的采样
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Job {
+    pub id: usize,
+    pub eye: usize,            // 立体渲染的眼睛序号
+    pub tile: Tile,            // 该眼图像中的图块
+    pub samples: Range<usize>, // 采样序号范围
+}
+
+/// 协调端发给工作进程的消息；连接建立后协调端先发送一行场景 JSON，之后逐行发送消息
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum Request {
+    Job(Job),
+    Done,
+}
+
+/// 工作进程返回的任务结果
+#[derive(Serialize, Deserialize)]
+struct JobResult {
+    id: usize,
+    sums: Vec<Color>, // 图块内各像素按行排列的、未经曝光的颜色之和
+}
+
+/// 分布式渲染的协调端：在 addr 上等待工作进程连接，把图像按图块与采样范围拆分为任务分发下去，
+/// 合并各任务返回的颜色之和并返回经过曝光的图像
+///
+/// 每项任务最多包含 samples_per_job 个采样（为 0 时不拆分采样）；任务先按采样范围、
+/// 再按图块排列，因此整幅图像逐步收敛。每项任务的随机序列是确定的，结果按固定顺序累加，
+/// 因此图像与工作进程的数量和完成先后无关。工作进程断开或无响应超过 WORKER_TIMEOUT 时
+/// 其未完成的任务重新排队，随时可以加入新的工作进程。每完成一项任务调用一次 on_progress；
+/// 设置了自动对焦时先在本地求出焦距再发送场景，对焦峰值显示在合并后于本地叠加
+pub fn coordinate(
+    file: &mut SceneFile,
+    addr: impl ToSocketAddrs,
+    samples_per_job: usize,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> io::Result<Image> {
+    let listener = TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    eprintln!("Coordinator listening on {}", listener.local_addr()?);
+
+    // 自动对焦与对焦峰值显示需要在本地构建场景
+    let camera = &file.camera;
+    let world = (camera.autofocus.is_some() || camera.focus_peaking.is_some())
+        .then(|| file.scene.build())
+        .transpose()?;
+    if let Some(world) = &world {
+        file.camera.auto_focus(&file.scene, world)?;
+    }
+    let scene_json = Arc::new(file.to_json());
+    let camera = &mut file.camera;
+    let spp = camera.samples_per_pixel;
+    let chunk = match samples_per_job {
+        0 => spp,
+        n => n.min(spp),
+    }
+    .max(1);
+    let tiles = camera.tiles();
+    let mut jobs = VecDeque::new();
+    for start in (0..spp).step_by(chunk) {
+        for &(eye, tile) in tiles.iter() {
+            jobs.push_back(Job {
+                id: jobs.len(),
+                eye,
+                tile,
+                samples: start..(start + chunk).min(spp),
+            });
+        }
+    }
+    let jobs_total = jobs.len();
+    let samples_total: u64 = jobs.iter().map(job_samples).sum();
+    let (width, height) = camera.output_size();
+    let mut sums = Image::new(width, height);
+
+    let queue = Arc::new(Mutex::new(jobs));
+    let finished = Arc::new(AtomicBool::new(false));
+    let (results, receiver) = mpsc::channel();
+    let mut pending = vec![None; jobs_total]; // 尚未收到结果的任务
+    let mut parked = BTreeMap::new(); // 已收到但尚未累加的结果
+    let mut next_chunk = vec![0; tiles.len()]; // 各图块下一段要累加的采样序号
+    for job in queue.lock().unwrap().iter() {
+        pending[job.id] = Some(job.clone());
+    }
+
+    let mut workers = Vec::new();
+    let mut jobs_done = 0;
+    let mut samples_done = 0;
+    let start = Instant::now();
+    while jobs_done < jobs_total {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                eprintln!("\rWorker {peer} connected");
+                let scene_json = Arc::clone(&scene_json);
+                let queue = Arc::clone(&queue);
+                let finished = Arc::clone(&finished);
+                let results = results.clone();
+                workers.push(thread::spawn(move || {
+                    if let Err(e) = serve_worker(stream, &scene_json, &queue, &results, &finished) {
+                        eprintln!("\rWorker {peer} failed: {e}");
+                    }
+                }));
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e),
+        }
+
+        let result: JobResult = match receiver.recv_timeout(Duration::from_millis(20)) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => unreachable!(), // results 仍在本线程中
+        };
+        let Some(job) = pending.get_mut(result.id).and_then(Option::take) else {
+            continue; // 重复的结果
+        };
+        // 同一图块的各段采样按序号顺序累加，保证结果与任务完成的先后无关
+        let t = job.id % tiles.len();
+        parked.insert(job.id, result.sums);
+        while let Some(tile_sums) = parked.remove(&(next_chunk[t] * tiles.len() + t)) {
+            let (eye, tile) = tiles[t];
+            sums.add_tile(&camera.output_tile(eye, tile), &tile_sums, 1.0);
+            next_chunk[t] += 1;
+        }
+
+        jobs_done += 1;
+        samples_done += job_samples(&job);
+        on_progress(&Progress::new(
+            camera.output_tile(job.eye, job.tile),
+            jobs_done,
+            jobs_total,
+            samples_done,
+            samples_total,
+            start.elapsed(),
+        ));
+    }
+
+    finished.store(true, Ordering::Relaxed);
+    for worker in workers {
+        let _ = worker.join();
+    }
+
+    let scale = camera.exposure() / spp as Real;
+    for pixel in sums.pixels.iter_mut() {
+        *pixel = *pixel * scale;
+    }
+    if let Some(world) = &world {
+        camera.overlay_focus_peaking(world, &mut sums);
+    }
+    Ok(sums)
+}
+
+/// 工作进程：连接协调端，读取场景后逐项渲染任务并返回结果，直到协调端发出结束消息
+pub fn work(addr: impl ToSocketAddrs) -> io::Result<()> {
+    let addrs: Vec<SocketAddr> = addr.to_socket_addrs()?.collect();
+    let mut attempt = 1;
+    let stream = loop {
+        match TcpStream::connect(&addrs[..]) {
+            Ok(stream) => break stream,
+            Err(_) if attempt < CONNECT_ATTEMPTS => {
+                attempt += 1;
+                thread::sleep(CONNECT_INTERVAL);
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+
+    let SceneFile {
+        mut camera, scene, ..
+    } = SceneFile::from_json(&read_line(&mut reader)?)?;
+    let world = scene.build()?;
+    camera.auto_focus(&scene, &world)?;
+    let mut jobs_done = 0;
+    while let Request::Job(job) = read_message(&mut reader)? {
+        let sums = camera.render_tile(&world, job.eye, job.tile, job.samples);
+        write_message(&mut writer, &JobResult { id: job.id, sums })?;
+        jobs_done += 1;
+    }
+    eprintln!("Worker finished {jobs_done} jobs");
+    Ok(())
+}
+
+/// 协调端中服务一个工作进程的线程：逐项取出任务发送并等待结果，出错或超时时把任务放回队列
+fn serve_worker(
+    stream: TcpStream,
+    scene_json: &str,
+    queue: &Mutex<VecDeque<Job>>,
+    results: &Sender<JobResult>,
+    finished: &AtomicBool,
+) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(WORKER_TIMEOUT))?;
+    stream.set_write_timeout(Some(WORKER_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    writer.write_all(scene_json.as_bytes())?;
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+
+    loop {
+        let Some(job) = queue.lock().unwrap().pop_front() else {
+            if finished.load(Ordering::Relaxed) {
+                return write_message(&mut writer, &Request::Done);
+            }
+            // 其他工作进程失败时任务可能重新排队
+            thread::sleep(Duration::from_millis(20));
+            continue;
+        };
+
+        let result = write_message(&mut writer, &Request::Job(job.clone()))
+            .and_then(|_| read_message::<JobResult>(&mut reader))
+            .and_then(|result| {
+                if result.id != job.id || result.sums.len() != job.tile.width * job.tile.height {
+                    return Err(invalid_data(format!("bad result for job {}", job.id)));
+                }
+                Ok(result)
+            });
+        match result {
+            Ok(result) => {
+                let _ = results.send(result);
+            }
+            Err(e) => {
+                queue.lock().unwrap().push_front(job);
+                return match e.kind() {
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("no response within {}s", WORKER_TIMEOUT.as_secs()),
+                    )),
+                    _ => Err(e),
+                };
+            }
+        }
+    }
+}
+
+/// 任务包含的像素采样数
+fn job_samples(job: &Job) -> u64 {
+    (job.tile.width * job.tile.height * job.samples.len()) as u64
+}
+
+/// 读取一行（不含换行符），连接关闭时返回 UnexpectedEof
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(line.trim_end().to_string())
+}
+
+fn read_message<T: DeserializeOwned>(reader: &mut impl BufRead) -> io::Result<T> {
+    Ok(serde_json::from_str(&read_line(reader)?)?)
+}
+
+fn write_message(writer: &mut impl Write, message: &impl Serialize) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, message)?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
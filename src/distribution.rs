@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+use crate::vec3::Real;
+
+/// 一维分段常数分布，只序列化函数值，读取时重新计算累积分布
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "Vec<Real>", into = "Vec<Real>")]
+pub struct Distribution1D {
+    func: Vec<Real>, // 各区间的函数值
+    cdf: Vec<Real>,  // 累积分布，长度为区间数 + 1
+    integral: Real,  // 函数在 [0, 1] 上的积分
+}
+
+impl Distribution1D {
+    pub fn new(func: &[Real]) -> Self {
+        let n = func.len();
+        let func: Vec<Real> = func.iter().map(|f| f.abs()).collect();
+
+        let mut cdf = vec![0.0; n + 1];
+        for i in 1..=n {
+            cdf[i] = cdf[i - 1] + func[i - 1] / n as Real;
+        }
+        let integral = cdf[n];
+        if integral == 0.0 {
+            // 函数全为零时退化为均匀分布
+            for (i, c) in cdf.iter_mut().enumerate() {
+                *c = i as Real / n as Real;
+            }
+        } else {
+            for c in cdf.iter_mut() {
+                *c /= integral;
+            }
+        }
+
+        Self {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    /// 区间数
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    /// 积分值
+    pub fn integral(&self) -> Real {
+        self.integral
+    }
+
+    /// 由均匀随机数 u 采样，返回 (x ∈ [0, 1), 概率密度, 区间下标)
+    pub fn sample_continuous(&self, u: Real) -> (Real, Real, usize) {
+        let offset = (self.cdf.partition_point(|&c| c <= u).max(1) - 1).min(self.count() - 1);
+
+        let mut du = u - self.cdf[offset];
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        if width > 0.0 {
+            du /= width;
+        }
+
+        let x = (offset as Real + du) / self.count() as Real;
+        (x, self.pdf(offset), offset)
+    }
+
+    /// 第 offset 个区间内的概率密度
+    pub fn pdf(&self, offset: usize) -> Real {
+        if self.integral > 0.0 {
+            self.func[offset] / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+impl From<Vec<Real>> for Distribution1D {
+    fn from(func: Vec<Real>) -> Self {
+        Self::new(&func)
+    }
+}
+
+impl From<Distribution1D> for Vec<Real> {
+    fn from(distribution: Distribution1D) -> Self {
+        distribution.func
+    }
+}
+
+/// 二维分段常数分布，先按行的边缘分布选行，再按行内条件分布选列
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>, // 每行的条件分布
+    marginal: Distribution1D,         // 行的边缘分布
+}
+
+impl Distribution2D {
+    /// func 按行存储，共 nv 行、每行 nu 个值，nu 与 nv 都必须大于 0
+    pub fn new(func: &[Real], nu: usize, nv: usize) -> Self {
+        assert!(nu > 0 && nv > 0, "empty 2D distribution ({nu}x{nv})");
+        let conditional: Vec<Distribution1D> =
+            func.chunks(nu).take(nv).map(Distribution1D::new).collect();
+        let marginal_func: Vec<Real> = conditional.iter().map(|d| d.integral()).collect();
+
+        Self {
+            conditional,
+            marginal: Distribution1D::new(&marginal_func),
+        }
+    }
+
+    /// 由两个均匀随机数采样，返回 (u, v, 概率密度)，u 为列方向、v 为行方向
+    pub fn sample_continuous(&self, u0: Real, u1: Real) -> (Real, Real, Real) {
+        let (v, pdf_v, row) = self.marginal.sample_continuous(u1);
+        let (u, pdf_u, _) = self.conditional[row].sample_continuous(u0);
+        (u, v, pdf_u * pdf_v)
+    }
+
+    /// (u, v) 处的概率密度
+    pub fn pdf(&self, u: Real, v: Real) -> Real {
+        let nv = self.marginal.count();
+        let row = ((v * nv as Real) as usize).min(nv - 1);
+        let nu = self.conditional[row].count();
+        let col = ((u * nu as Real) as usize).min(nu - 1);
+
+        if self.marginal.integral() > 0.0 {
+            self.conditional[row].func[col] / self.marginal.integral()
+        } else {
+            1.0
+        }
+    }
+}
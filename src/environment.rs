@@ -0,0 +1,159 @@
+use std::{io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    distribution::Distribution2D,
+    image::{invalid_data, Image},
+    math::Mat3,
+    rng,
+    vec3::{consts::PI, Color, Real, Vec3},
+};
+
+/// 环境光（无穷远处的背景光源）
+#[typetag::serde(tag = "type")]
+pub trait Environment: Sync {
+    /// 沿方向 direction 射向无穷远处的光线接收到的辐亮度
+    fn radiance(&self, direction: &Vec3) -> Color;
+
+    /// 重要性采样一个入射方向，返回 (单位方向, 立体角概率密度)；不支持采样时返回 None
+    fn sample(&self) -> Option<(Vec3, Real)> {
+        None
+    }
+
+    /// 采样到方向 direction 的立体角概率密度
+    fn pdf(&self, direction: &Vec3) -> Real {
+        let _ = direction;
+        0.0
+    }
+}
+
+/// 白色到蓝色的天空渐变
+#[derive(Default, Serialize, Deserialize)]
+pub struct Gradient;
+
+#[typetag::serde]
+impl Environment for Gradient {
+    fn radiance(&self, direction: &Vec3) -> Color {
+        let unit_direction = direction.unit_vector();
+        let a = 0.5 * (unit_direction.y + 1.0);
+        (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.5, 0.7, 1.0)
+    }
+}
+
+/// 等距柱状投影的 HDR 环境贴图，按亮度重要性采样
+///
+/// 贴图中心对应 -z 方向，顶部对应 +y 方向；采样分布不序列化，读取时由贴图重新计算
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "EnvironmentMapData")]
+pub struct EnvironmentMap {
+    pub rotation: Real,  // 绕 y 轴旋转角度（度）
+    pub intensity: Real, // 亮度倍数
+    image: Image,
+    #[serde(skip_serializing)]
+    distribution: Distribution2D, // 按亮度与 sinθ 加权的采样分布
+}
+
+#[derive(Deserialize)]
+struct EnvironmentMapData {
+    rotation: Real,
+    intensity: Real,
+    image: Image,
+}
+
+impl TryFrom<EnvironmentMapData> for EnvironmentMap {
+    type Error = io::Error;
+
+    fn try_from(data: EnvironmentMapData) -> io::Result<Self> {
+        Ok(Self {
+            rotation: data.rotation,
+            intensity: data.intensity,
+            ..Self::new(data.image)?
+        })
+    }
+}
+
+impl EnvironmentMap {
+    /// 由贴图创建环境光，贴图的宽或高为 0 时返回错误
+    pub fn new(image: Image) -> io::Result<Self> {
+        if image.width == 0 || image.height == 0 || image.pixels.len() != image.width * image.height
+        {
+            return Err(invalid_data(format!(
+                "invalid environment map size {}x{}",
+                image.width, image.height
+            )));
+        }
+        let mut func = Vec::with_capacity(image.pixels.len());
+        for y in 0..image.height {
+            let sin_theta = (PI * (y as Real + 0.5) / image.height as Real).sin();
+            for x in 0..image.width {
+                func.push(image.get(x, y).luminance().max(0.0) * sin_theta);
+            }
+        }
+        let distribution = Distribution2D::new(&func, image.width, image.height);
+
+        Ok(Self {
+            rotation: 0.0,
+            intensity: 1.0,
+            image,
+            distribution,
+        })
+    }
+
+    /// 读取 .hdr 或 .pfm 环境贴图
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::new(Image::load(path)?)
+    }
+
+    /// 世界方向 -> 贴图坐标 (u, v)
+    fn direction_to_uv(&self, direction: &Vec3) -> (Real, Real) {
+        let d = Mat3::rotation_y(-self.rotation.to_radians()) * direction.unit_vector();
+        let phi = d.x.atan2(-d.z);
+        let theta = d.y.clamp(-1.0, 1.0).acos();
+        (0.5 + phi / (2.0 * PI), theta / PI)
+    }
+
+    /// 贴图坐标 (u, v) -> 世界方向
+    fn uv_to_direction(&self, u: Real, v: Real) -> Vec3 {
+        let phi = (u - 0.5) * 2.0 * PI;
+        let theta = v * PI;
+        let d = Vec3::new(
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            -theta.sin() * phi.cos(),
+        );
+        Mat3::rotation_y(self.rotation.to_radians()) * d
+    }
+}
+
+#[typetag::serde]
+impl Environment for EnvironmentMap {
+    fn radiance(&self, direction: &Vec3) -> Color {
+        let (u, v) = self.direction_to_uv(direction);
+        let x = ((u * self.image.width as Real) as usize).min(self.image.width - 1);
+        let y = ((v * self.image.height as Real) as usize).min(self.image.height - 1);
+        self.image.get(x, y) * self.intensity
+    }
+
+    fn sample(&self) -> Option<(Vec3, Real)> {
+        let (u, v, pdf_uv) = self
+            .distribution
+            .sample_continuous(rng::random(), rng::random());
+        let sin_theta = (v * PI).sin();
+        if pdf_uv <= 0.0 || sin_theta <= 0.0 {
+            return None;
+        }
+
+        let pdf = pdf_uv / (2.0 * PI * PI * sin_theta); // 由 (u, v) 面积密度换算为立体角密度
+        Some((self.uv_to_direction(u, v), pdf))
+    }
+
+    fn pdf(&self, direction: &Vec3) -> Real {
+        let (u, v) = self.direction_to_uv(direction);
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+}
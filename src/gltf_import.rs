@@ -0,0 +1,461 @@
+use std::{collections::HashMap, fs, io, path::Path, rc::Rc, sync::Arc};
+
+use gltf::{
+    camera::Projection as GltfProjection, image::Format, khr_lights_punctual::Kind, mesh::Mode,
+    Document, Gltf,
+};
+
+use crate::{
+    bvh::Bvh,
+    camera::Camera,
+    hittable::{Hittable, HittableList},
+    image::{invalid_data, srgb_to_linear, Image},
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    math::{Mat4, Transform},
+    mesh::Mesh,
+    projection::Orthographic,
+    scene::{Node, Scene},
+    sphere::Sphere,
+    texture::{ImageTexture, Texture},
+    vec3::{consts::PI, Color, Point3, Real, Vec3},
+};
+
+/// 支持的 glTF 扩展，其余扩展被必需时拒绝导入，被使用时给出警告
+const SUPPORTED_EXTENSIONS: [&str; 4] = [
+    "KHR_lights_punctual",
+    "KHR_materials_emissive_strength",
+    "KHR_materials_ior",
+    "KHR_materials_transmission",
+];
+
+/// 点光源与聚光灯用发光小球近似，球的半径（物体空间单位）
+const LIGHT_RADIUS: Real = 0.05;
+
+/// glTF 中的相机，换算为 Camera 的参数
+pub struct ImportedCamera {
+    pub name: String,
+    pub vfov: Real,                        // 垂直视角（度），正交相机为 0
+    pub lookfrom: Point3,                  // 观察点
+    pub lookat: Point3,                    // 观察目标
+    pub vup: Vec3,                         // 观察向上
+    pub aspect_ratio: Option<Real>,        // 长宽比
+    pub orthographic_height: Option<Real>, // 正交相机的视口高度
+}
+
+impl ImportedCamera {
+    /// 把相机参数写入 camera
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.lookfrom = self.lookfrom;
+        camera.lookat = self.lookat;
+        camera.vup = self.vup;
+        if let Some(aspect_ratio) = self.aspect_ratio {
+            camera.aspect_ratio = aspect_ratio;
+        }
+        match self.orthographic_height {
+            Some(height) => camera.projection = Box::new(Orthographic { height }),
+            None => camera.vfov = self.vfov,
+        }
+    }
+}
+
+/// 导入的 glTF 场景
+pub struct GltfImport {
+    pub scene: Scene,
+    pub cameras: Vec<ImportedCamera>,
+    pub warnings: Vec<String>, // 导入时忽略或近似处理的内容
+}
+
+impl GltfImport {
+    /// 读取 .gltf 或 .glb 文件中的默认场景
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let gltf = Gltf::from_slice_without_validation(&fs::read(path)?)
+            .map_err(|e| gltf_error(path, e))?;
+        let mut warnings = Vec::new();
+        let unsupported: Vec<&str> = gltf
+            .extensions_required()
+            .filter(|ext| !SUPPORTED_EXTENSIONS.contains(ext))
+            .collect();
+        if !unsupported.is_empty() {
+            return Err(invalid_data(format!(
+                "{}: unsupported required glTF extensions: {}",
+                path.display(),
+                unsupported.join(", ")
+            )));
+        }
+        for ext in gltf.extensions_used() {
+            if !SUPPORTED_EXTENSIONS.contains(&ext) {
+                warnings.push(format!("ignoring unsupported glTF extension {ext}"));
+            }
+        }
+
+        let (document, buffers, images) = gltf::import(path).map_err(|e| gltf_error(path, e))?;
+        let mut importer = Importer {
+            buffers,
+            images: images
+                .iter()
+                .map(|data| Arc::new(convert_image(data)))
+                .collect(),
+            meshes: HashMap::new(),
+            materials: HashMap::new(),
+            result: Self {
+                scene: Scene::default(),
+                cameras: Vec::new(),
+                warnings,
+            },
+        };
+        importer.import(&document);
+        Ok(importer.result)
+    }
+}
+
+/// 导入过程中的状态，网格与材质按下标缓存以便共享
+struct Importer {
+    buffers: Vec<gltf::buffer::Data>,
+    images: Vec<Arc<Image>>,
+    meshes: HashMap<usize, Option<Rc<dyn Hittable>>>,
+    materials: HashMap<Option<usize>, Rc<dyn Material>>,
+    result: GltfImport,
+}
+
+impl Importer {
+    fn import(&mut self, document: &Document) {
+        let Some(gltf_scene) = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+        else {
+            self.warn("file contains no scene".to_string());
+            return;
+        };
+        let mut root = Node::group(gltf_scene.name().unwrap_or("gltf"));
+        for node in gltf_scene.nodes() {
+            if let Some(child) = self.node(&node, &Transform::default()) {
+                root.add(child);
+            }
+        }
+        self.result.scene.add(root);
+    }
+
+    fn warn(&mut self, message: String) {
+        self.result.warnings.push(message);
+    }
+
+    /// 导入节点及其子树，parent 为父节点在世界空间的变换
+    fn node(&mut self, node: &gltf::Node, parent: &Transform) -> Option<Node> {
+        let name = node
+            .name()
+            .map_or_else(|| format!("node_{}", node.index()), str::to_string);
+        let m = node.transform().matrix(); // 列优先
+        let Some(transform) = Transform::new(Mat4::new(std::array::from_fn(|i| {
+            std::array::from_fn(|j| m[j][i] as Real)
+        }))) else {
+            self.warn(format!(
+                "node {name} has a singular transform and was skipped"
+            ));
+            return None;
+        };
+        let world = parent * transform;
+
+        let mut result = Node::group(&name).with_transform(transform);
+        if let Some(mesh) = node.mesh() {
+            result.object = self.mesh(&mesh);
+        }
+        if let Some(camera) = node.camera() {
+            self.camera(&camera, &name, &world);
+        }
+        if let Some(light) = node.light() {
+            if let Some(light) = self.light(&light) {
+                result.add(light);
+            }
+        }
+        for child in node.children() {
+            if let Some(child) = self.node(&child, &world) {
+                result.add(child);
+            }
+        }
+        Some(result)
+    }
+
+    /// 网格的所有三角形图元，没有可用图元时为 None
+    fn mesh(&mut self, mesh: &gltf::Mesh) -> Option<Rc<dyn Hittable>> {
+        if let Some(object) = self.meshes.get(&mesh.index()) {
+            return object.clone();
+        }
+
+        let name = mesh
+            .name()
+            .map_or_else(|| format!("mesh_{}", mesh.index()), str::to_string);
+        let mut list = HittableList::new();
+        for primitive in mesh.primitives() {
+            if primitive.mode() != Mode::Triangles {
+                self.warn(format!(
+                    "mesh {name}: {:?} primitives are not supported",
+                    primitive.mode()
+                ));
+                continue;
+            }
+            let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+            let Some(positions) = reader.read_positions() else {
+                self.warn(format!("mesh {name}: primitive without positions"));
+                continue;
+            };
+            let mut data = Mesh {
+                positions: positions.map(|p| Point3(to_vec3(p))).collect(),
+                normals: reader
+                    .read_normals()
+                    .map_or_else(Vec::new, |normals| normals.map(to_vec3).collect()),
+                uvs: reader.read_tex_coords(0).map_or_else(Vec::new, |uvs| {
+                    // glTF 的 v 轴向下
+                    uvs.into_f32()
+                        .map(|[u, v]| (u as Real, 1.0 - v as Real))
+                        .collect()
+                }),
+                colors: Vec::new(),
+                indices: Vec::new(),
+            };
+            let indices: Vec<usize> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+                None => (0..data.positions.len()).collect(),
+            };
+            if indices.iter().any(|&i| i >= data.positions.len()) {
+                self.warn(format!("mesh {name}: vertex index out of range"));
+                continue;
+            }
+            data.indices = indices
+                .chunks_exact(3)
+                .map(|face| [face[0], face[1], face[2]])
+                .collect();
+            let material = self.material(&primitive.material());
+            list.add(Rc::new(data.build(material)));
+        }
+
+        let object: Option<Rc<dyn Hittable>> = match list.objects.len() {
+            0 => None,
+            1 => list.objects.pop(),
+            _ => Some(Rc::new(Bvh::new(list))),
+        };
+        self.meshes.insert(mesh.index(), object.clone());
+        object
+    }
+
+    /// 把金属度-粗糙度材质映射到渲染器的材质：
+    /// 发光 -> DiffuseLight，透射 -> Dielectric，金属 -> Metal，其余 -> Lambertian；
+    /// 未使用的贴图、透明模式与单面属性记入警告
+    fn material(&mut self, material: &gltf::Material) -> Rc<dyn Material> {
+        if let Some(mat) = self.materials.get(&material.index()) {
+            return mat.clone();
+        }
+
+        let name = match material.index() {
+            Some(index) => material
+                .name()
+                .map_or_else(|| format!("material_{index}"), str::to_string),
+            None => "default".to_string(),
+        };
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, _] = pbr.base_color_factor();
+        let base_color = Color::new(r as Real, g as Real, b as Real);
+        let strength = material.emissive_strength().unwrap_or(1.0) as Real;
+        let emissive = Color(to_vec3(material.emissive_factor()) * strength);
+        let transmission = material
+            .transmission()
+            .map_or(0.0, |t| t.transmission_factor());
+        let mut used_emissive_texture = false;
+        let mut used_base_color_texture = false;
+
+        let mat: Rc<dyn Material> = if !emissive.near_zero() {
+            used_emissive_texture = true;
+            let emit = match material.emissive_texture() {
+                Some(info) => self.texture(&name, info.texture(), info.tex_coord(), emissive),
+                None => None,
+            };
+            Rc::new(match emit {
+                Some(texture) => DiffuseLight::from_texture(texture),
+                None => DiffuseLight::new(emissive),
+            })
+        } else if transmission >= 0.5 {
+            Rc::new(Dielectric::new(material.ior().unwrap_or(1.5) as Real))
+        } else if pbr.metallic_factor() >= 0.5 {
+            Rc::new(Metal::new(base_color, pbr.roughness_factor() as Real))
+        } else {
+            used_base_color_texture = true;
+            let albedo = match pbr.base_color_texture() {
+                Some(info) => self.texture(&name, info.texture(), info.tex_coord(), base_color),
+                None => None,
+            };
+            Rc::new(match albedo {
+                Some(texture) => Lambertian::from_texture(texture),
+                None => Lambertian::new(base_color),
+            })
+        };
+
+        let ignored_textures = [
+            (
+                "base color",
+                pbr.base_color_texture().is_some() && !used_base_color_texture,
+            ),
+            (
+                "emissive",
+                material.emissive_texture().is_some() && !used_emissive_texture,
+            ),
+            ("normal", material.normal_texture().is_some()),
+            ("occlusion", material.occlusion_texture().is_some()),
+            (
+                "metallic-roughness",
+                pbr.metallic_roughness_texture().is_some(),
+            ),
+            (
+                "transmission",
+                material
+                    .transmission()
+                    .is_some_and(|t| t.transmission_texture().is_some()),
+            ),
+        ];
+        for (kind, _) in ignored_textures.iter().filter(|(_, ignored)| *ignored) {
+            self.warn(format!("material {name}: {kind} texture is ignored"));
+        }
+        if material.alpha_mode() != gltf::material::AlphaMode::Opaque {
+            self.warn(format!(
+                "material {name}: alpha mode {:?} is ignored, rendering as opaque",
+                material.alpha_mode()
+            ));
+        }
+        if !material.double_sided() {
+            self.warn(format!(
+                "material {name}: single-sided material is rendered double-sided"
+            ));
+        }
+
+        let mat = self.result.scene.materials.add(&name, mat);
+        self.materials.insert(material.index(), mat.clone());
+        mat
+    }
+
+    /// 以 tint 着色的图像纹理，只支持第 0 组纹理坐标
+    fn texture(
+        &mut self,
+        material: &str,
+        texture: gltf::Texture,
+        tex_coord: u32,
+        tint: Color,
+    ) -> Option<Arc<dyn Texture>> {
+        if tex_coord != 0 {
+            self.warn(format!(
+                "material {material}: texture coordinate set {tex_coord} is not supported"
+            ));
+            return None;
+        }
+        let image = texture.source();
+        let name = image
+            .name()
+            .map_or_else(|| format!("image_{}", image.index()), str::to_string);
+        let texture =
+            Arc::new(ImageTexture::new(self.images[image.index()].clone()).with_tint(tint));
+        Some(self.result.scene.textures.add(&name, texture))
+    }
+
+    /// 记录世界空间变换为 world 的节点上的相机，相机朝向物体空间的 -z 方向
+    fn camera(&mut self, camera: &gltf::Camera, node: &str, world: &Transform) {
+        let (vfov, aspect_ratio, orthographic_height) = match camera.projection() {
+            GltfProjection::Perspective(p) => (
+                (p.yfov() as Real).to_degrees(),
+                p.aspect_ratio().map(|a| a as Real),
+                None,
+            ),
+            GltfProjection::Orthographic(o) => (
+                0.0,
+                Some((o.xmag() / o.ymag()) as Real),
+                Some(2.0 * o.ymag() as Real),
+            ),
+        };
+        self.result.cameras.push(ImportedCamera {
+            name: camera.name().unwrap_or(node).to_string(),
+            vfov,
+            lookfrom: world.point(&Point3::new(0.0, 0.0, 0.0)),
+            lookat: world.point(&Point3::new(0.0, 0.0, -1.0)),
+            vup: world.vector(&Vec3::new(0.0, 1.0, 0.0)),
+            aspect_ratio,
+            orthographic_height,
+        });
+    }
+
+    /// 点光源与聚光灯近似为发光小球，节点以光源名称命名，可用于光源链接
+    fn light(&mut self, light: &gltf::khr_lights_punctual::Light) -> Option<Node> {
+        let name = light
+            .name()
+            .map_or_else(|| format!("light_{}", light.index()), str::to_string);
+        match light.kind() {
+            Kind::Directional => {
+                self.warn(format!(
+                    "light {name}: directional lights are not supported"
+                ));
+                return None;
+            }
+            Kind::Spot { .. } => {
+                self.warn(format!("light {name}: spot light cone is ignored"));
+            }
+            Kind::Point => {}
+        }
+        // 半径为 r、辐亮度为 L 的球在各方向的发光强度为 πr²L
+        let radiance =
+            to_vec3(light.color()) * light.intensity() as Real / (PI * LIGHT_RADIUS * LIGHT_RADIUS);
+        let material = self
+            .result
+            .scene
+            .materials
+            .add(&name, Rc::new(DiffuseLight::new(Color(radiance))));
+        Some(Node::object(
+            &name,
+            Rc::new(Sphere::new(Point3::default(), LIGHT_RADIUS, material)),
+        ))
+    }
+}
+
+fn to_vec3([x, y, z]: [f32; 3]) -> Vec3 {
+    Vec3::new(x as Real, y as Real, z as Real)
+}
+
+fn gltf_error(path: &Path, err: gltf::Error) -> io::Error {
+    match err {
+        gltf::Error::Io(err) => err,
+        err => invalid_data(format!("{}: {err}", path.display())),
+    }
+}
+
+/// 把 glTF 解码得到的图像转换为线性颜色，8 位与 16 位通道按 sRGB 编码处理
+fn convert_image(data: &gltf::image::Data) -> Image {
+    let (channels, bytes) = match data.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let channel = |pixel: &[u8], c: usize| -> Real {
+        let b = &pixel[c * bytes..(c + 1) * bytes];
+        match bytes {
+            1 => srgb_to_linear(b[0] as Real / 255.0),
+            2 => srgb_to_linear(u16::from_le_bytes([b[0], b[1]]) as Real / 65535.0),
+            _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as Real,
+        }
+    };
+
+    let mut image = Image::new(data.width as usize, data.height as usize);
+    for (color, pixel) in image
+        .pixels
+        .iter_mut()
+        .zip(data.pixels.chunks_exact(channels * bytes))
+    {
+        *color = if channels < 3 {
+            let l = channel(pixel, 0); // 灰度（及透明度）
+            Color::new(l, l, l)
+        } else {
+            Color::new(channel(pixel, 0), channel(pixel, 1), channel(pixel, 2))
+        };
+    }
+    image
+}
@@ -1,74 +1,323 @@
 use std::rc::Rc;
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
+    aabb::Aabb,
     interval::Interval,
-    material::{Lambertian, Material},
-    ray::Ray,
-    vec3::Vec3,
+    light::LightSet,
+    material::Material,
+    math::{offset_ray_origin, Transform},
+    packet::{
+        hit_each, hit_lanes, occluded_each, PacketRecords, RayPacket, DIVERGENCE_THRESHOLD,
+        PACKET_WIDTH,
+    },
+    ray::{Ray, RayKind},
+    simd::Mask,
+    vec3::{Color, Normal3, Point3, Real, Vec3},
 };
 
+/// 求交得到的交点，只保存求交时顺带得到的量，复制代价很低；
+/// 位置、法线等在确定最近交点后由 resolve 计算
+#[derive(Clone, Copy)]
+pub struct SurfaceInteraction<'a> {
+    pub t: Real,                      // 光线参数
+    pub uv: (Real, Real),             // 求交时得到的表面参数（三角形为重心坐标）
+    pub mat: &'a dyn Material,        // 材质
+    pub links: LightSet,              // 照亮该表面的光源
+    pub light: LightSet,              // 表面发光时所属的光源
+    shape: &'a dyn Shape,             // 被击中的几何体
+    transform: Option<&'a Transform>, // 几何体所在实例的物体到世界变换
+}
+
+impl<'a> SurfaceInteraction<'a> {
+    pub fn new(t: Real, uv: (Real, Real), mat: &'a dyn Material, shape: &'a dyn Shape) -> Self {
+        Self {
+            t,
+            uv,
+            mat,
+            links: LightSet::ALL,
+            light: LightSet::UNLINKED,
+            shape,
+            transform: None,
+        }
+    }
+
+    /// 标记交点位于经过 transform 变换的实例上
+    ///
+    /// 交点只记录一层变换，因此要求 self 尚无变换；Instance::new 拒绝嵌套的实例，保证了这一点
+    pub fn with_transform(self, transform: &'a Transform) -> Self {
+        Self {
+            transform: Some(transform),
+            ..self
+        }
+    }
+
+    /// 计算交点的完整几何信息
+    pub fn resolve(&self, r: &Ray) -> HitRecord<'a> {
+        match self.transform {
+            Some(transform) => self
+                .shape
+                .surface(&transform.inverse().ray(r), self)
+                .transform(r, transform),
+            None => self.shape.surface(r, self),
+        }
+    }
+}
+
+/// 可由交点计算表面几何信息的几何体
+pub trait Shape {
+    /// 由光线 r 与交点 si 计算位置、法线、纹理坐标等
+    fn surface<'a>(&self, r: &Ray, si: &SurfaceInteraction<'a>) -> HitRecord<'a>;
+}
+
+/// 交点的完整几何信息，供材质着色使用
 #[derive(Clone)]
-pub struct HitRecord {
-    pub p: Vec3,               // 交点
-    pub normal: Vec3,          // 法线
-    pub t: f64,                // 光线参数
+pub struct HitRecord<'a> {
+    pub p: Point3,             // 交点
+    pub p_error: Vec3,         // 交点坐标的绝对误差上界
+    pub normal: Normal3,       // 法线
+    pub t: Real,               // 光线参数
+    pub u: Real,               // 纹理坐标
+    pub v: Real,               // 纹理坐标
+    pub color: Color,          // 插值得到的顶点颜色，没有顶点颜色时为白色
     pub front_face: bool,      // 是否是正面
-    pub mat: Rc<dyn Material>, // 材质
+    pub mat: &'a dyn Material, // 材质
+    pub links: LightSet,       // 照亮该表面的光源
+    pub light: LightSet,       // 表面发光时所属的光源
 }
 
+/// 可被光线击中的物体，序列化时以 type 字段区分具体类型
+#[typetag::serde(tag = "type")]
 pub trait Hittable {
-    /// 判断光线是否击中物体
-    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool;
+    /// 光线在区间 ray_t 内的最近交点
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<SurfaceInteraction<'_>>;
+
+    /// 光线在区间 ray_t 内是否击中任何表面，只需判断有无交点，找到一个即可返回；
+    /// 默认借助 hit 实现
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.hit(r, ray_t).is_some()
+    }
+
+    /// 世界坐标系下的包围盒；默认为无界，无界的物体不进入 BVH 的树，每条光线都与之求交
+    fn bounding_box(&self) -> Aabb {
+        Aabb::UNIVERSE
+    }
+
+    /// 是否为实例或包含实例，Instance::new 据此拒绝嵌套的实例
+    fn contains_instance(&self) -> bool {
+        false
+    }
+
+    /// 光线包求交：为每条活跃光线寻找 (t_min, t_max) 内的最近交点，击中时收缩 t_max
+    /// 并写入 recs 中对应的记录，返回本次被击中的光线；默认逐条调用 hit
+    fn hit_packet<'a>(
+        &'a self,
+        packet: &mut RayPacket<PACKET_WIDTH>,
+        recs: &mut PacketRecords<'a>,
+    ) -> Mask<PACKET_WIDTH> {
+        hit_each(self, packet, recs)
+    }
+
+    /// 光线包的遮挡查询，返回被遮挡的活跃光线；默认逐条调用 occluded
+    fn occluded_packet(&self, packet: &RayPacket<PACKET_WIDTH>) -> Mask<PACKET_WIDTH> {
+        occluded_each(self, packet)
+    }
 }
 
-impl HitRecord {
+impl<'a> HitRecord<'a> {
+    /// 由交点 si 处的位置与外法线创建记录，法线朝向光线来的一侧
+    pub fn new(
+        r: &Ray,
+        si: &SurfaceInteraction<'a>,
+        p: Point3,
+        p_error: Vec3,
+        outward_normal: &Vec3,
+        (u, v): (Real, Real),
+    ) -> Self {
+        let mut rec = Self {
+            p,
+            p_error,
+            normal: Normal3::default(),
+            t: si.t,
+            u,
+            v,
+            color: Color::new(1.0, 1.0, 1.0),
+            front_face: false,
+            mat: si.mat,
+            links: si.links,
+            light: si.light,
+        };
+        rec.set_face_normal(r, outward_normal);
+        rec
+    }
+
     /// 设置法线
     pub fn set_face_normal(&mut self, r: &Ray, outward_noraml: &Vec3) {
         self.front_face = r.direction.dot(outward_noraml) < 0.0; // 判断是否是正面
         self.normal = if self.front_face {
-            outward_noraml.clone()
+            Normal3(*outward_noraml)
         } else {
-            -outward_noraml.clone()
+            Normal3(-outward_noraml)
         }; // 如果是正面，法线方向不变，否则取反
     }
+
+    /// 把物体空间的记录变换到世界空间，r 为世界空间的光线
+    pub fn transform(self, r: &Ray, transform: &Transform) -> Self {
+        let outward_normal = if self.front_face {
+            self.normal
+        } else {
+            Normal3(-self.normal.0)
+        };
+        let (p, p_error) = transform.point_with_error(&self.p, &self.p_error);
+        let mut rec = Self { p, p_error, ..self };
+        rec.set_face_normal(r, &transform.normal(&outward_normal));
+        rec
+    }
+
+    /// 从交点沿 direction 发出类型为 kind 的新光线，起点按误差上界偏移到表面的对应一侧
+    pub fn spawn_ray(&self, direction: Vec3, kind: RayKind) -> Ray {
+        Ray::new(
+            offset_ray_origin(&self.p, &self.p_error, &self.normal, &direction),
+            direction,
+            kind,
+        )
+    }
 }
 
+/// 物体列表，只序列化物体本身，包围盒在读取时重新计算
+#[derive(Serialize, Deserialize)]
+#[serde(from = "HittableListData")]
 pub struct HittableList {
+    pub objects: Vec<Rc<dyn Hittable>>,
+    #[serde(skip_serializing)]
+    bbox: Aabb, // 所有物体的包围盒
+}
+
+#[derive(Deserialize)]
+struct HittableListData {
     objects: Vec<Rc<dyn Hittable>>,
 }
 
+impl From<HittableListData> for HittableList {
+    fn from(data: HittableListData) -> Self {
+        let mut list = HittableList::new();
+        for object in data.objects {
+            list.add(object);
+        }
+        list
+    }
+}
+
+impl Default for HittableList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HittableList {
     pub fn new() -> HittableList {
         HittableList {
             objects: Vec::new(),
+            bbox: Aabb::EMPTY,
         }
     }
 
     pub fn add(&mut self, object: Rc<dyn Hittable>) {
+        self.bbox = self.bbox.union(&object.bounding_box());
         self.objects.push(object);
     }
 }
 
+#[typetag::serde]
 impl Hittable for HittableList {
-    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
-        let temp_rec = &mut HitRecord {
-            p: Vec3::default(),
-            normal: Vec3::default(),
-            t: 0.0,
-            front_face: false,
-            mat: Rc::new(Lambertian::new(Vec3::default())),
-        };
-        let mut hit_anything = false;
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<SurfaceInteraction<'_>> {
+        let mut closest = None;
         let mut closest_so_far = ray_t.max;
 
         for object in self.objects.iter() {
-            if object.hit(r, Interval::new(ray_t.min, closest_so_far), temp_rec) {
-                hit_anything = true;
-                closest_so_far = temp_rec.t;
-                *rec = temp_rec.clone();
+            if let Some(si) = object.hit(r, Interval::new(ray_t.min, closest_so_far)) {
+                closest_so_far = si.t;
+                closest = Some(si);
+            }
+        }
+
+        closest
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.objects.iter().any(|object| object.occluded(r, ray_t))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn contains_instance(&self) -> bool {
+        self.objects.iter().any(|object| object.contains_instance())
+    }
+
+    /// 逐个物体先用包围盒筛选光线；进入某个物体包围盒的光线发散后，改为逐条求交该物体
+    fn hit_packet<'a>(
+        &'a self,
+        packet: &mut RayPacket<PACKET_WIDTH>,
+        recs: &mut PacketRecords<'a>,
+    ) -> Mask<PACKET_WIDTH> {
+        if packet.diverged() {
+            return hit_each(self, packet, recs);
+        }
+
+        let inv_direction = packet.inv_direction();
+        let active = packet.active;
+        let mut hits = Mask([false; PACKET_WIDTH]);
+        for object in self.objects.iter() {
+            let lanes = object.bounding_box().hit_packet(packet, &inv_direction);
+            if !lanes.any() {
+                continue;
+            }
+            if lanes.count() <= DIVERGENCE_THRESHOLD {
+                hits = hits.or(hit_lanes(lanes, packet, recs, |r, ray_t| {
+                    object.hit(r, ray_t)
+                }));
+            } else {
+                packet.active = lanes;
+                hits = hits.or(object.hit_packet(packet, recs));
+                packet.active = active;
             }
         }
+        hits
+    }
+
+    fn occluded_packet(&self, packet: &RayPacket<PACKET_WIDTH>) -> Mask<PACKET_WIDTH> {
+        if packet.diverged() {
+            return occluded_each(self, packet);
+        }
 
-        hit_anything
+        // 已被遮挡的光线不再参与后续求交
+        let inv_direction = packet.inv_direction();
+        let mut remaining = *packet;
+        let mut occluded = Mask([false; PACKET_WIDTH]);
+        for object in self.objects.iter() {
+            let lanes = object.bounding_box().hit_packet(&remaining, &inv_direction);
+            if !lanes.any() {
+                continue;
+            }
+            if lanes.count() <= DIVERGENCE_THRESHOLD {
+                for (i, o) in occluded.0.iter_mut().enumerate() {
+                    if lanes.0[i] && object.occluded(&packet.ray(i), packet.interval(i)) {
+                        *o = true;
+                    }
+                }
+            } else {
+                let mut subset = remaining;
+                subset.active = lanes;
+                occluded = occluded.or(object.occluded_packet(&subset));
+            }
+            remaining.active = packet.active.and(!occluded);
+            if !remaining.active.any() {
+                break;
+            }
+        }
+        occluded
     }
 }
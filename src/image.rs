@@ -0,0 +1,507 @@
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    progress::Tile,
+    vec3::{Color, Real},
+};
+
+/// 浮点 RGB 图像，按行自上而下存储
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "ImageData")]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+#[derive(Deserialize)]
+struct ImageData {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl TryFrom<ImageData> for Image {
+    type Error = io::Error;
+
+    /// 拒绝宽或高为 0、像素数与尺寸不符的图像
+    fn try_from(data: ImageData) -> io::Result<Self> {
+        let count = check_size(data.width, data.height, 1)?;
+        if data.pixels.len() != count {
+            return Err(invalid_data(format!(
+                "image of size {}x{} has {} pixels",
+                data.width,
+                data.height,
+                data.pixels.len()
+            )));
+        }
+        Ok(Self {
+            width: data.width,
+            height: data.height,
+            pixels: data.pixels,
+        })
+    }
+}
+
+impl Image {
+    /// 创建全黑图像
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Color::default(); width * height],
+        }
+    }
+
+    /// 像素 (x, y)
+    pub fn get(&self, x: usize, y: usize) -> &Color {
+        &self.pixels[y * self.width + x]
+    }
+
+    /// 把按行排列的 colors 乘以 scale 后累加到区域 tile 中
+    pub fn add_tile(&mut self, tile: &Tile, colors: &[Color], scale: Real) {
+        for (j, row_colors) in colors.chunks(tile.width).enumerate() {
+            let row = (tile.y + j) * self.width + tile.x;
+            for (pixel, color) in self.pixels[row..row + tile.width]
+                .iter_mut()
+                .zip(row_colors)
+            {
+                *pixel += color * scale;
+            }
+        }
+    }
+
+    /// 以 gamma 2 编码写出 8 位 ASCII PPM 图像，超出 [0, 1] 的分量被截断
+    pub fn write_ppm(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut out = BufWriter::new(out);
+        writeln!(out, "P3\n{} {}\n255", self.width, self.height)?;
+        for pixel in self.pixels.iter() {
+            let [r, g, b] = to_rgb8(pixel);
+            writeln!(out, "{r} {g} {b}")?;
+        }
+        out.flush()
+    }
+
+    /// 以 gamma 2 编码写出 24 位 BMP 图像，编码方式同 write_ppm
+    pub fn write_bmp(&self, out: &mut impl Write) -> io::Result<()> {
+        let row_size = (3 * self.width).div_ceil(4) * 4; // 每行按 4 字节对齐
+        let data_size = row_size * self.height;
+        let mut out = BufWriter::new(out);
+        // 文件头
+        out.write_all(b"BM")?;
+        out.write_all(&(54 + data_size as u32).to_le_bytes())?;
+        out.write_all(&0u32.to_le_bytes())?;
+        out.write_all(&54u32.to_le_bytes())?;
+        // BITMAPINFOHEADER，高度取负表示自上而下存储
+        out.write_all(&40u32.to_le_bytes())?;
+        out.write_all(&(self.width as i32).to_le_bytes())?;
+        out.write_all(&(-(self.height as i32)).to_le_bytes())?;
+        out.write_all(&1u16.to_le_bytes())?;
+        out.write_all(&24u16.to_le_bytes())?;
+        out.write_all(&[0; 24])?; // 不压缩，其余字段取 0
+        let mut row = vec![0; row_size];
+        for pixels in self.pixels.chunks(self.width.max(1)) {
+            for (bgr, pixel) in row.chunks_exact_mut(3).zip(pixels) {
+                let [r, g, b] = to_rgb8(pixel);
+                bgr.copy_from_slice(&[b, g, r]);
+            }
+            out.write_all(&row)?;
+        }
+        out.flush()
+    }
+
+    /// 按扩展名读取图像：.hdr（Radiance RGBE）、.pfm，其余按 PNM 处理；宽或高为 0 的图像返回错误
+    pub fn load(path: &Path) -> io::Result<Image> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("hdr") => Self::load_hdr(path),
+            Some("pfm") => Self::load_pfm(path),
+            _ => Self::load_pnm(path),
+        }
+    }
+
+    /// 读取 Radiance RGBE (.hdr) 图像，支持未压缩与新式游程编码的扫描线
+    pub fn load_hdr(path: &Path) -> io::Result<Image> {
+        let data = fs::read(path)?;
+        let mut pos = 0;
+
+        let magic = read_line(&data, &mut pos)?;
+        if !magic.starts_with("#?") {
+            return Err(invalid_data("missing Radiance header".to_string()));
+        }
+        loop {
+            let line = read_line(&data, &mut pos)?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    return Err(invalid_data(format!(
+                        "unsupported Radiance format {format}"
+                    )));
+                }
+            }
+        }
+
+        // 仅支持标准方向 "-Y height +X width"
+        let resolution = read_line(&data, &mut pos)?;
+        let fields: Vec<&str> = resolution.split_whitespace().collect();
+        let (height, width) = match fields.as_slice() {
+            ["-Y", h, "+X", w] => (
+                h.parse()
+                    .map_err(|_| invalid_data(format!("invalid resolution {resolution}")))?,
+                w.parse()
+                    .map_err(|_| invalid_data(format!("invalid resolution {resolution}")))?,
+            ),
+            _ => return Err(invalid_data(format!("unsupported resolution {resolution}"))),
+        };
+        check_size(width, height, 4)?;
+        // 分配图像前确认数据至少能容纳这么多扫描线，以免伪造的头部耗尽内存
+        let min_bytes = height.checked_mul(min_hdr_scanline_bytes(width));
+        if min_bytes.is_none_or(|n| n > data.len().saturating_sub(pos)) {
+            return Err(invalid_data("truncated Radiance data".to_string()));
+        }
+
+        let mut image = Image::new(width, height);
+        let mut scanline = vec![0u8; width * 4];
+        for y in 0..height {
+            read_hdr_scanline(&data, &mut pos, &mut scanline)?;
+            for x in 0..width {
+                let rgbe = &scanline[x * 4..x * 4 + 4];
+                image.pixels[y * width + x] = if rgbe[3] == 0 {
+                    Color::default()
+                } else {
+                    let f = ((rgbe[3] as i32 - 136) as Real).exp2(); // 2^(e - 128 - 8)
+                    Color::new(
+                        rgbe[0] as Real * f,
+                        rgbe[1] as Real * f,
+                        rgbe[2] as Real * f,
+                    )
+                };
+            }
+        }
+
+        Ok(image)
+    }
+
+    /// 读取 PFM 浮点图像（PF 彩色 / Pf 灰度），文件中的行自下而上存储
+    pub fn load_pfm(path: &Path) -> io::Result<Image> {
+        let data = fs::read(path)?;
+        let mut pos = 0;
+
+        let magic = next_token(&data, &mut pos)?;
+        let channels = match magic.as_str() {
+            "PF" => 3,
+            "Pf" => 1,
+            _ => return Err(invalid_data(format!("unsupported PFM format {magic}"))),
+        };
+        let width = parse_header_value(&data, &mut pos)?;
+        let height = parse_header_value(&data, &mut pos)?;
+        let scale_token = next_token(&data, &mut pos)?;
+        let scale: Real = scale_token
+            .parse()
+            .map_err(|_| invalid_data(format!("invalid PFM scale {scale_token}")))?;
+        let little_endian = scale < 0.0;
+        pos += 1;
+
+        let count = check_size(width, height, channels)?;
+        let raw = values_at(&data, pos, count, 4)
+            .ok_or_else(|| invalid_data("truncated PFM data".to_string()))?;
+        let values: Vec<Real> = raw
+            .chunks(4)
+            .map(|b| {
+                let bytes = [b[0], b[1], b[2], b[3]];
+                if little_endian {
+                    f32::from_le_bytes(bytes) as Real
+                } else {
+                    f32::from_be_bytes(bytes) as Real
+                }
+            })
+            .collect();
+
+        let mut image = Image::new(width, height);
+        for (row, chunk) in values.chunks(width * channels).enumerate() {
+            let y = height - 1 - row;
+            for x in 0..width {
+                let c = &chunk[x * channels..(x + 1) * channels];
+                image.pixels[y * width + x] = if channels == 1 {
+                    Color::new(c[0], c[0], c[0])
+                } else {
+                    Color::new(c[0], c[1], c[2])
+                };
+            }
+        }
+
+        Ok(image)
+    }
+
+    /// 读取 PNM 图像（P2/P3/P5/P6），像素值按 maxval 归一化到 [0, 1]，不做伽马转换
+    pub fn load_pnm(path: &Path) -> io::Result<Image> {
+        let data = fs::read(path)?;
+        let mut pos = 0;
+
+        let magic = next_token(&data, &mut pos)?;
+        let (channels, binary) = match magic.as_str() {
+            "P2" => (1, false),
+            "P3" => (3, false),
+            "P5" => (1, true),
+            "P6" => (3, true),
+            _ => return Err(invalid_data(format!("unsupported PNM format {magic}"))),
+        };
+        let width = parse_header_value(&data, &mut pos)?;
+        let height = parse_header_value(&data, &mut pos)?;
+        let maxval = parse_header_value(&data, &mut pos)?;
+        if maxval == 0 || maxval > 65535 {
+            return Err(invalid_data(format!("invalid PNM maxval {maxval}")));
+        }
+
+        let count = check_size(width, height, channels)?;
+        let mut values = Vec::with_capacity(count.min(data.len()));
+        if binary {
+            pos += 1; // 头部之后紧跟一个空白字符
+            let bytes_per_value = if maxval > 255 { 2 } else { 1 };
+            let raw = values_at(&data, pos, count, bytes_per_value)
+                .ok_or_else(|| invalid_data("truncated PNM data".to_string()))?;
+            for chunk in raw.chunks(bytes_per_value) {
+                let value = chunk.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+                values.push(value);
+            }
+        } else {
+            for _ in 0..count {
+                values.push(parse_header_value(&data, &mut pos)?);
+            }
+        }
+
+        let scale = 1.0 / maxval as Real;
+        let pixels = values
+            .chunks(channels)
+            .map(|c| {
+                if channels == 1 {
+                    let g = c[0] as Real * scale;
+                    Color::new(g, g, g)
+                } else {
+                    Color::new(
+                        c[0] as Real * scale,
+                        c[1] as Real * scale,
+                        c[2] as Real * scale,
+                    )
+                }
+            })
+            .collect();
+
+        Ok(Image {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+/// sRGB 编码的分量转换为线性值
+pub fn srgb_to_linear(c: Real) -> Real {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub(crate) fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// 检查图像头部给出的尺寸，拒绝宽或高为 0 以及数据量溢出的图像，返回分量总数
+fn check_size(width: usize, height: usize, channels: usize) -> io::Result<usize> {
+    if width == 0 || height == 0 {
+        return Err(invalid_data(format!("invalid image size {width}x{height}")));
+    }
+    width
+        .checked_mul(height)
+        .and_then(|n| n.checked_mul(channels))
+        .ok_or_else(|| invalid_data(format!("image size {width}x{height} is too large")))
+}
+
+/// data 中从 pos 开始的 count 个 size 字节的值，数据不足或字节数溢出时返回 None
+fn values_at(data: &[u8], pos: usize, count: usize, size: usize) -> Option<&[u8]> {
+    let end = count.checked_mul(size)?.checked_add(pos)?;
+    data.get(pos..end)
+}
+
+/// 宽为 width 的 Radiance 扫描线至少占用的字节数：游程编码时每个通道每 127 个像素至少 2 字节，
+/// 否则每个像素 4 字节
+fn min_hdr_scanline_bytes(width: usize) -> usize {
+    let raw = width * 4;
+    if (8..32768).contains(&width) {
+        raw.min(4 + 8 * width.div_ceil(127))
+    } else {
+        raw
+    }
+}
+
+/// 线性颜色 -> gamma 2 编码的 8 位 RGB，超出 [0, 1] 的分量被截断
+fn to_rgb8(pixel: &Color) -> [u8; 3] {
+    [pixel.x, pixel.y, pixel.z].map(|c| {
+        let c = c.sqrt(); // 线性值 -> gamma 2
+        (256.0 * c.clamp(0.0, 0.999)) as u8
+    })
+}
+
+/// 读取下一个以空白分隔的记号，跳过 # 注释
+fn next_token(data: &[u8], pos: &mut usize) -> io::Result<String> {
+    loop {
+        while *pos < data.len() && data[*pos].is_ascii_whitespace() {
+            *pos += 1;
+        }
+        if *pos < data.len() && data[*pos] == b'#' {
+            while *pos < data.len() && data[*pos] != b'\n' {
+                *pos += 1;
+            }
+            continue;
+        }
+        break;
+    }
+
+    let start = *pos;
+    while *pos < data.len() && !data[*pos].is_ascii_whitespace() {
+        *pos += 1;
+    }
+    if start == *pos {
+        return Err(invalid_data("unexpected end of PNM data".to_string()));
+    }
+    Ok(String::from_utf8_lossy(&data[start..*pos]).into_owned())
+}
+
+/// 读取一行文本（不含换行符）
+fn read_line(data: &[u8], pos: &mut usize) -> io::Result<String> {
+    let start = *pos;
+    while *pos < data.len() && data[*pos] != b'\n' {
+        *pos += 1;
+    }
+    if *pos >= data.len() {
+        return Err(invalid_data(
+            "unexpected end of Radiance header".to_string(),
+        ));
+    }
+    let line = String::from_utf8_lossy(&data[start..*pos])
+        .trim()
+        .to_string();
+    *pos += 1;
+    Ok(line)
+}
+
+/// 读取一条 RGBE 扫描线到 scanline（每像素 4 字节）
+fn read_hdr_scanline(data: &[u8], pos: &mut usize, scanline: &mut [u8]) -> io::Result<()> {
+    let width = scanline.len() / 4;
+    let truncated = || invalid_data("truncated Radiance data".to_string());
+    let head = data.get(*pos..*pos + 4).ok_or_else(truncated)?;
+
+    let is_rle = (8..32768).contains(&width) && head[0] == 2 && head[1] == 2 && head[2] & 0x80 == 0;
+    if !is_rle {
+        // 未压缩扫描线
+        let raw = data.get(*pos..*pos + width * 4).ok_or_else(truncated)?;
+        scanline.copy_from_slice(raw);
+        *pos += width * 4;
+        return Ok(());
+    }
+    if ((head[2] as usize) << 8 | head[3] as usize) != width {
+        return Err(invalid_data("Radiance scanline width mismatch".to_string()));
+    }
+    *pos += 4;
+
+    // 四个通道分别游程编码
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = *data.get(*pos).ok_or_else(truncated)? as usize;
+            *pos += 1;
+            if count > 128 {
+                let run = count - 128;
+                let value = *data.get(*pos).ok_or_else(truncated)?;
+                *pos += 1;
+                if x + run > width {
+                    return Err(invalid_data("bad Radiance run length".to_string()));
+                }
+                for _ in 0..run {
+                    scanline[x * 4 + channel] = value;
+                    x += 1;
+                }
+            } else {
+                if count == 0 || x + count > width {
+                    return Err(invalid_data("bad Radiance run length".to_string()));
+                }
+                let raw = data.get(*pos..*pos + count).ok_or_else(truncated)?;
+                for &value in raw {
+                    scanline[x * 4 + channel] = value;
+                    x += 1;
+                }
+                *pos += count;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_header_value(data: &[u8], pos: &mut usize) -> io::Result<usize> {
+    let token = next_token(data, pos)?;
+    token
+        .parse()
+        .map_err(|_| invalid_data(format!("invalid PNM value {token}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 把 data 写入临时文件后按扩展名读取
+    fn load(name: &str, data: &[u8]) -> io::Result<Image> {
+        let path = std::env::temp_dir().join(format!("ray-tracing-{}-{name}", std::process::id()));
+        fs::write(&path, data).unwrap();
+        let image = Image::load(&path);
+        fs::remove_file(&path).unwrap();
+        image
+    }
+
+    #[test]
+    fn forged_sizes_are_rejected_before_allocating() {
+        let huge = "100000000 100000000";
+        for (name, header) in [
+            (
+                "huge.hdr",
+                "#?RADIANCE\n\n-Y 100000000 +X 100000000\n".to_string(),
+            ),
+            ("huge.pfm", format!("PF\n{huge}\n-1.0\n")),
+            ("huge.ppm", format!("P6\n{huge}\n255\n")),
+            ("overflow.pfm", format!("PF\n{} 1\n-1.0\n", usize::MAX / 3)),
+            ("overflow.ppm", format!("P6\n{} 1\n65535\n", usize::MAX / 3)),
+        ] {
+            let mut data = header.into_bytes();
+            data.extend([0u8; 64]);
+            assert!(load(name, &data).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn small_images_load() {
+        let image = load("small.ppm", b"P3\n2 1\n255\n255 0 0 0 0 255\n").unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(
+            image.pixels,
+            [Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.0, 1.0)]
+        );
+        let image = load(
+            "small.pfm",
+            &[b"Pf\n1 1\n-1.0\n".as_slice(), &0.5f32.to_le_bytes()].concat(),
+        )
+        .unwrap();
+        assert_eq!(image.pixels, [Color::new(0.5, 0.5, 0.5)]);
+    }
+}
@@ -0,0 +1,75 @@
+use std::{io, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aabb::Aabb,
+    hittable::{Hittable, SurfaceInteraction},
+    image::invalid_data,
+    interval::Interval,
+    math::Transform,
+    ray::Ray,
+};
+
+/// 经过仿射变换的物体：求交时把光线变换到物体空间，交点再变换回世界空间；
+/// 被变换的物体中不能再包含实例
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "InstanceData")]
+pub struct Instance {
+    object: Rc<dyn Hittable>,
+    transform: Transform, // 物体空间到世界空间
+    #[serde(skip_serializing)]
+    bbox: Aabb, // 世界空间的包围盒
+}
+
+#[derive(Deserialize)]
+struct InstanceData {
+    object: Rc<dyn Hittable>,
+    transform: Transform,
+}
+
+impl TryFrom<InstanceData> for Instance {
+    type Error = io::Error;
+
+    fn try_from(data: InstanceData) -> io::Result<Self> {
+        Instance::new(data.object, data.transform)
+    }
+}
+
+impl Instance {
+    /// 创建实例，object 中已包含实例时返回错误
+    pub fn new(object: Rc<dyn Hittable>, transform: Transform) -> io::Result<Self> {
+        if object.contains_instance() {
+            return Err(invalid_data(
+                "instances cannot be nested; combine the transforms instead".to_string(),
+            ));
+        }
+        let bbox = object.bounding_box().transform(&transform);
+        Ok(Self {
+            object,
+            transform,
+            bbox,
+        })
+    }
+}
+
+#[typetag::serde]
+impl Hittable for Instance {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<SurfaceInteraction<'_>> {
+        let si = self.object.hit(&self.transform.inverse().ray(r), ray_t)?;
+        Some(si.with_transform(&self.transform))
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.object
+            .occluded(&self.transform.inverse().ray(r), ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn contains_instance(&self) -> bool {
+        true
+    }
+}
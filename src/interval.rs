@@ -1,23 +1,49 @@
-#[derive(Clone)]
+use overload::overload;
+use std::ops;
+
+use crate::vec3::Real;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Interval {
-    pub min: f64,
-    pub max: f64,
+    pub min: Real,
+    pub max: Real,
 }
 
 impl Interval {
-    pub fn new(min: f64, max: f64) -> Self {
+    /// 空区间
+    pub const EMPTY: Interval = Interval {
+        min: Real::INFINITY,
+        max: Real::NEG_INFINITY,
+    };
+
+    /// 整个实数轴
+    pub const UNIVERSE: Interval = Interval {
+        min: Real::NEG_INFINITY,
+        max: Real::INFINITY,
+    };
+
+    pub fn new(min: Real, max: Real) -> Self {
         Self { min, max }
     }
 
-    pub fn contains(&self, x: f64) -> bool {
+    /// 长度，空区间为负
+    pub fn size(&self) -> Real {
+        self.max - self.min
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min > self.max
+    }
+
+    pub fn contains(&self, x: Real) -> bool {
         self.min <= x && x <= self.max
     }
 
-    pub fn surrounds(&self, x: f64) -> bool {
+    pub fn surrounds(&self, x: Real) -> bool {
         self.min < x && x < self.max
     }
 
-    pub fn clamp(&self, x: f64) -> f64 {
+    pub fn clamp(&self, x: Real) -> Real {
         if x < self.min {
             return self.min;
         }
@@ -26,23 +52,57 @@ impl Interval {
         }
         x
     }
+
+    /// 两端各向外扩展 delta / 2
+    pub fn expand(&self, delta: Real) -> Self {
+        let padding = delta / 2.0;
+        Self::new(self.min - padding, self.max + padding)
+    }
+
+    /// 同时包含两个区间的最小区间
+    pub fn union(&self, other: &Interval) -> Self {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    /// 两个区间的交集，不相交时为空区间
+    pub fn intersection(&self, other: &Interval) -> Self {
+        Self::new(self.min.max(other.min), self.max.min(other.max))
+    }
+
+    /// 两个区间是否有公共部分
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        !self.intersection(other).is_empty()
+    }
 }
 
 impl Default for Interval {
     fn default() -> Self {
-        Interval {
-            min: f64::NEG_INFINITY,
-            max: f64::INFINITY,
-        }
+        Interval::UNIVERSE
     }
 }
 
-pub static EMPTY: Interval = Interval {
-    min: f64::INFINITY,
-    max: f64::NEG_INFINITY,
-};
+// 平移
+overload!((a: ?Interval) + (b: ?Real) -> Interval { Interval::new(a.min + b, a.max + b) });
+overload!((a: ?Real) + (b: ?Interval) -> Interval { b + a });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-pub static UNIVERSE: Interval = Interval {
-    min: f64::NEG_INFINITY,
-    max: f64::INFINITY,
-};
+    #[test]
+    fn set_operations() {
+        let a = Interval::new(0.0, 2.0);
+        let b = Interval::new(1.0, 4.0);
+        assert_eq!(a.union(&b), Interval::new(0.0, 4.0));
+        assert_eq!(a.intersection(&b), Interval::new(1.0, 2.0));
+        assert!(a.overlaps(&b));
+        assert!(!a.overlaps(&Interval::new(3.0, 4.0)));
+        assert!(a.intersection(&Interval::new(3.0, 4.0)).is_empty());
+        assert_eq!(Interval::EMPTY.union(&a), a);
+        assert_eq!(a.expand(1.0), Interval::new(-0.5, 2.5));
+        assert_eq!(a + 1.0, Interval::new(1.0, 3.0));
+        assert_eq!(a.size(), 2.0);
+        assert_eq!(a.clamp(5.0), 2.0);
+        assert!(a.contains(2.0) && !a.surrounds(2.0));
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    distribution::Distribution1D,
+    image::Image,
+    rng,
+    vec3::{consts::PI, Real, Vec3},
+};
+
+/// 光圈形状，采样结果位于单位圆内
+#[derive(Serialize, Deserialize)]
+pub enum Aperture {
+    Circular,                                  // 圆形光圈
+    Polygon { blades: usize, rotation: Real }, // 正多边形光圈：叶片数、旋转角度（度）
+    Mask(ApertureMask),                        // 由图像决定透光率的光圈
+}
+
+impl Aperture {
+    /// 在光圈上随机采样一点
+    pub fn sample(&self) -> (Real, Real) {
+        match self {
+            Aperture::Circular => {
+                let p = Vec3::random_in_unit_disk();
+                (p.x, p.y)
+            }
+            Aperture::Polygon { blades, rotation } => {
+                sample_polygon((*blades).max(3), rotation.to_radians())
+            }
+            Aperture::Mask(mask) => mask.sample(),
+        }
+    }
+}
+
+/// 在内接于单位圆的正多边形内均匀采样
+fn sample_polygon(blades: usize, rotation: Real) -> (Real, Real) {
+    let k = ((rng::random::<Real>() * blades as Real) as usize).min(blades - 1); // 选择一个三角形
+    let a0 = rotation + 2.0 * PI * k as Real / blades as Real;
+    let a1 = rotation + 2.0 * PI * (k + 1) as Real / blades as Real;
+
+    // 三角形 (0, v0, v1) 内均匀采样
+    let su = rng::random::<Real>().sqrt();
+    let sv = rng::random::<Real>();
+    let b0 = su * (1.0 - sv);
+    let b1 = su * sv;
+    (b0 * a0.cos() + b1 * a1.cos(), b0 * a0.sin() + b1 * a1.sin())
+}
+
+/// 图像光圈遮罩，像素亮度即透光率，图像铺满 [-1, 1]² 区域
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "ApertureMaskData")]
+pub struct ApertureMask {
+    width: usize,
+    height: usize,
+    distribution: Distribution1D, // 按像素亮度的分布
+}
+
+#[derive(Deserialize)]
+struct ApertureMaskData {
+    width: usize,
+    height: usize,
+    distribution: Distribution1D,
+}
+
+impl TryFrom<ApertureMaskData> for ApertureMask {
+    type Error = String;
+
+    /// 拒绝宽或高为 0、分布的区间数与尺寸不符的遮罩
+    fn try_from(data: ApertureMaskData) -> Result<Self, String> {
+        let count = data.distribution.count();
+        if data.width == 0 || data.height == 0 || data.width.checked_mul(data.height) != Some(count)
+        {
+            return Err(format!(
+                "aperture mask of size {}x{} has {count} values",
+                data.width, data.height
+            ));
+        }
+        Ok(Self {
+            width: data.width,
+            height: data.height,
+            distribution: data.distribution,
+        })
+    }
+}
+
+impl ApertureMask {
+    pub fn new(image: &Image) -> Self {
+        let luminance: Vec<Real> = image
+            .pixels
+            .iter()
+            .map(|p| p.luminance().max(0.0))
+            .collect();
+        Self {
+            width: image.width,
+            height: image.height,
+            distribution: Distribution1D::new(&luminance),
+        }
+    }
+
+    /// 按透光率采样遮罩上一点，遮罩全黑时返回中心
+    pub fn sample(&self) -> (Real, Real) {
+        if self.distribution.integral() <= 0.0 {
+            return (0.0, 0.0);
+        }
+
+        let (_, _, index) = self.distribution.sample_continuous(rng::random());
+        let px = (index % self.width) as Real + rng::random::<Real>();
+        let py = (index / self.width) as Real + rng::random::<Real>();
+        (
+            2.0 * px / self.width as Real - 1.0,
+            1.0 - 2.0 * py / self.height as Real,
+        )
+    }
+}
+
+/// Brown–Conrady 镜头畸变，作用于归一化相机坐标（除以焦距后的像平面坐标）
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct LensDistortion {
+    pub k1: Real, // 径向畸变系数
+    pub k2: Real,
+    pub k3: Real,
+    pub p1: Real, // 切向畸变系数
+    pub p2: Real,
+}
+
+impl LensDistortion {
+    /// 理想坐标 -> 畸变坐标
+    pub fn distort(&self, x: Real, y: Real) -> (Real, Real) {
+        let r2 = x * x + y * y;
+        let radial = 1.0 + r2 * (self.k1 + r2 * (self.k2 + r2 * self.k3));
+        let (dx, dy) = self.tangential(x, y, r2);
+        (x * radial + dx, y * radial + dy)
+    }
+
+    /// 畸变坐标 -> 理想坐标，不动点迭代求解
+    pub fn undistort(&self, xd: Real, yd: Real) -> (Real, Real) {
+        let (mut x, mut y) = (xd, yd);
+        for _ in 0..20 {
+            let r2 = x * x + y * y;
+            let radial = 1.0 + r2 * (self.k1 + r2 * (self.k2 + r2 * self.k3));
+            let (dx, dy) = self.tangential(x, y, r2);
+            x = (xd - dx) / radial;
+            y = (yd - dy) / radial;
+        }
+        (x, y)
+    }
+
+    /// 切向畸变量
+    fn tangential(&self, x: Real, y: Real, r2: Real) -> (Real, Real) {
+        (
+            2.0 * self.p1 * x * y + self.p2 * (r2 + 2.0 * x * x),
+            self.p1 * (r2 + 2.0 * y * y) + 2.0 * self.p2 * x * y,
+        )
+    }
+}
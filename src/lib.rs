@@ -0,0 +1,118 @@
+//! 路径追踪渲染器
+//!
+//! 场景由实现 [`Hittable`] 的物体组成，物体表面的着色由 [`Material`] 决定，
+//! [`Camera`] 负责生成光线并输出图像。通常用 [`Scene`] 组织节点层级与材质库，
+//! 再以 [`Scene::build`] 编译为加速结构后交给相机渲染：
+//!
+//! ```no_run
+//! use std::rc::Rc;
+//!
+//! use ray_tracing::{Camera, Color, Lambertian, Node, Point3, Scene, Sphere};
+//!
+//! let mut scene = Scene::default();
+//! let material = scene
+//!     .materials
+//!     .add("red", Rc::new(Lambertian::new(Color::new(0.8, 0.1, 0.1))));
+//! scene.add(Node::object(
+//!     "ball",
+//!     Rc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 0.5, material)),
+//! ));
+//!
+//! let mut camera = Camera::default();
+//! camera.lookfrom = Point3::new(0.0, 0.0, 2.0);
+//! camera.render(&scene.build()?)?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! # 扩展
+//!
+//! 自定义的几何体、材质、纹理、投影与环境光分别实现 [`Hittable`]（及 [`Shape`]）、
+//! [`Material`]、[`Texture`]、[`Projection`] 与 [`Environment`]。这些 trait 通过
+//! [typetag](https://docs.rs/typetag) 支持场景文件的序列化，实现时需同时实现
+//! `serde` 的 `Serialize` 与 `Deserialize`，并在 impl 上标注 `#[typetag::serde]`：
+//!
+//! ```
+//! use ray_tracing::{Color, HitRecord, Material, Ray, RayKind, Vec3};
+//! use serde::{Deserialize, Serialize};
+//!
+//! /// 只显示法线方向的材质
+//! #[derive(Serialize, Deserialize)]
+//! struct NormalShade;
+//!
+//! #[typetag::serde]
+//! impl Material for NormalShade {
+//!     fn scatter(
+//!         &self,
+//!         _r_in: &Ray,
+//!         rec: &HitRecord,
+//!         attenuation: &mut Color,
+//!         scattered: &mut Ray,
+//!     ) -> bool {
+//!         *attenuation = Color(0.5 * (*rec.normal + Vec3::new(1.0, 1.0, 1.0)));
+//!         *scattered = rec.spawn_ray(*rec.normal, RayKind::Reflection);
+//!         true
+//!     }
+//! }
+//! ```
+
+pub mod aabb;
+pub mod animation;
+pub mod bvh;
+pub mod camera;
+pub mod demo;
+pub mod distributed;
+pub mod distribution;
+pub mod environment;
+pub mod gltf_import;
+pub mod hittable;
+pub mod image;
+pub mod instance;
+pub mod interval;
+pub mod lens;
+pub mod library;
+pub mod light;
+pub mod material;
+pub mod math;
+pub mod mesh;
+pub mod packet;
+mod ply;
+pub mod preview;
+pub mod progress;
+pub mod projection;
+pub mod ray;
+pub mod rng;
+pub mod scene;
+pub mod simd;
+pub mod sky;
+pub mod sphere;
+pub mod stats;
+mod stl;
+pub mod texture;
+pub mod triangle;
+pub mod vec3;
+
+pub use aabb::Aabb;
+pub use animation::{
+    Animation, Channel, Interpolation, Keyframe, Target, Track, TransformComponent,
+};
+pub use bvh::Bvh;
+pub use camera::{AutoFocus, Camera, Crop, CropWindow};
+pub use environment::{Environment, EnvironmentMap, Gradient};
+pub use hittable::{HitRecord, Hittable, HittableList, Shape, SurfaceInteraction};
+pub use image::Image;
+pub use instance::Instance;
+pub use interval::Interval;
+pub use material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+pub use math::Transform;
+pub use mesh::{Mesh, TriangleMesh};
+pub use preview::CameraCommand;
+pub use progress::{CancelToken, Progress, Tile};
+pub use projection::{Equirectangular, Fisheye, Orthographic, Projection, ThinLens};
+pub use ray::{Ray, RayKind};
+pub use scene::{Node, Scene, SceneFile};
+pub use sky::Sky;
+pub use sphere::Sphere;
+pub use stats::Stats;
+pub use texture::{Checker, ImageTexture, SolidColor, Texture, VertexColor};
+pub use triangle::Triangle;
+pub use vec3::{Color, Normal3, Point3, Real, Vec3};
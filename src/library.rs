@@ -0,0 +1,212 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    marker::PhantomData,
+    rc::Rc,
+    sync::Arc,
+    thread::LocalKey,
+};
+
+use serde::{
+    de::{self, DeserializeOwned, MapAccess, Visitor},
+    Deserializer, Serialize, Serializer,
+};
+use serde_json::Value;
+
+use crate::{material::Material, texture::Texture};
+
+/// 按名称索引的资源库，按名称排序以便序列化结果稳定
+///
+/// 随场景序列化时，库中资源被物体或其他资源引用处只写出名称，
+/// 读取时名称解析为库中的同一份资源，因此共享关系在保存与读取后保持不变
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct Library<T: Clone> {
+    items: BTreeMap<String, T>,
+}
+
+impl<T: Clone> Default for Library<T> {
+    fn default() -> Self {
+        Self {
+            items: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: Clone> Library<T> {
+    /// 以 name 登记资源，返回该资源，便于直接用于构建物体；同名资源会被替换
+    pub fn add(&mut self, name: &str, item: T) -> T {
+        self.items.insert(name.to_string(), item.clone());
+        item
+    }
+
+    pub fn get(&self, name: &str) -> Option<T> {
+        self.items.get(name).cloned()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.items.contains_key(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.items.keys().map(String::as_str)
+    }
+}
+
+impl<T: Shared> Library<T> {
+    /// 登记库中全部资源，此后序列化时对它们的引用只写出名称
+    pub(crate) fn register(&self) {
+        T::registry().with_borrow_mut(|registry| {
+            for (name, item) in self.items.iter() {
+                registry.names.insert(item.address(), name.clone());
+            }
+        });
+    }
+
+    /// 由序列化后的各资源读取资源库；资源之间按名称的引用与它们的先后顺序无关
+    pub(crate) fn load(values: BTreeMap<String, Value>) -> Result<Self, String> {
+        let names: Vec<String> = values.keys().cloned().collect();
+        T::registry().with_borrow_mut(|registry| registry.pending.extend(values));
+        let mut library = Self::default();
+        for name in names {
+            library.add(&name, resolve(&name)?);
+        }
+        Ok(library)
+    }
+}
+
+/// 可按名称引用的共享资源
+pub trait Shared: Clone + Serialize + DeserializeOwned + 'static {
+    const KIND: &'static str; // 资源类别，用于错误信息
+
+    /// 当前线程登记该类资源的表
+    fn registry() -> &'static LocalKey<RefCell<Registry<Self>>>;
+
+    /// 资源的地址，共享同一份资源的引用地址相同
+    fn address(&self) -> usize;
+}
+
+impl Shared for Rc<dyn Material> {
+    const KIND: &'static str = "material";
+
+    fn registry() -> &'static LocalKey<RefCell<Registry<Self>>> {
+        &MATERIALS
+    }
+
+    fn address(&self) -> usize {
+        Rc::as_ptr(self) as *const () as usize
+    }
+}
+
+impl Shared for Arc<dyn Texture> {
+    const KIND: &'static str = "texture";
+
+    fn registry() -> &'static LocalKey<RefCell<Registry<Self>>> {
+        &TEXTURES
+    }
+
+    fn address(&self) -> usize {
+        Arc::as_ptr(self) as *const () as usize
+    }
+}
+
+/// 序列化或读取资源库期间登记的资源
+pub struct Registry<T> {
+    names: HashMap<usize, String>,   // 序列化：资源地址 -> 名称
+    items: HashMap<String, T>,       // 读取：名称 -> 已读取的资源
+    pending: HashMap<String, Value>, // 读取：名称 -> 尚未读取的资源
+}
+
+impl<T> Default for Registry<T> {
+    fn default() -> Self {
+        Self {
+            names: HashMap::new(),
+            items: HashMap::new(),
+            pending: HashMap::new(),
+        }
+    }
+}
+
+thread_local! {
+    static MATERIALS: RefCell<Registry<Rc<dyn Material>>> = RefCell::new(Registry::default());
+    static TEXTURES: RefCell<Registry<Arc<dyn Texture>>> = RefCell::new(Registry::default());
+}
+
+/// 在 f 执行期间使用空的登记表，结束后（包括 panic）恢复之前的登记表
+pub(crate) fn scope<R>(f: impl FnOnce() -> R) -> R {
+    struct Guard {
+        materials: Registry<Rc<dyn Material>>,
+        textures: Registry<Arc<dyn Texture>>,
+    }
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            MATERIALS.set(std::mem::take(&mut self.materials));
+            TEXTURES.set(std::mem::take(&mut self.textures));
+        }
+    }
+
+    let _guard = Guard {
+        materials: MATERIALS.take(),
+        textures: TEXTURES.take(),
+    };
+    f()
+}
+
+/// 名为 name 的库中资源，尚未读取时先读取它；资源循环引用时报告未知名称
+fn resolve<T: Shared>(name: &str) -> Result<T, String> {
+    let (item, value) = T::registry().with_borrow_mut(|registry| {
+        (
+            registry.items.get(name).cloned(),
+            registry.pending.remove(name),
+        )
+    });
+    if let Some(item) = item {
+        return Ok(item);
+    }
+    let value = value.ok_or_else(|| format!("unknown {} {name:?}", T::KIND))?;
+    // 读取过程中可能递归解析其他资源，因此不能持有登记表的借用
+    let item: T = serde_json::from_value(value).map_err(|e| format!("{} {name}: {e}", T::KIND))?;
+    T::registry().with_borrow_mut(|registry| registry.items.insert(name.to_string(), item.clone()));
+    Ok(item)
+}
+
+/// 用于 `#[serde(with = "crate::library::reference")]`：
+/// 已登记的库中资源写为名称，其余资源完整写出；读取时两种形式均可
+pub(crate) mod reference {
+    use super::*;
+
+    pub fn serialize<T: Shared, S: Serializer>(item: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        let name =
+            T::registry().with_borrow(|registry| registry.names.get(&item.address()).cloned());
+        match name {
+            Some(name) => serializer.serialize_str(&name),
+            None => item.serialize(serializer),
+        }
+    }
+
+    pub fn deserialize<'de, T: Shared, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        struct ReferenceVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Shared> Visitor<'de> for ReferenceVisitor<T> {
+            type Value = T;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a {} or the name of one in the library", T::KIND)
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<T, E> {
+                resolve(name).map_err(E::custom)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<T, A::Error> {
+                T::deserialize(de::value::MapAccessDeserializer::new(map))
+            }
+        }
+
+        deserializer.deserialize_any(ReferenceVisitor(PhantomData))
+    }
+}
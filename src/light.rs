@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+/// 光源集合，以光源编号为位的掩码表示
+///
+/// 0 号为环境光，1 ~ 62 号分配给被链接的发光物体，63 号代表所有未被链接的发光物体
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LightSet(pub u64);
+
+impl Default for LightSet {
+    fn default() -> Self {
+        LightSet::ALL
+    }
+}
+
+impl LightSet {
+    pub const NONE: LightSet = LightSet(0);
+    pub const ALL: LightSet = LightSet(u64::MAX);
+
+    /// 环境光
+    pub const ENVIRONMENT: LightSet = LightSet(1);
+
+    /// 未被链接的发光物体
+    pub const UNLINKED: LightSet = LightSet(1 << 63);
+
+    /// 可分配给发光物体的最大编号
+    pub const MAX_LIGHT_ID: usize = 62;
+
+    /// 只包含编号为 id 的光源；id 超过 MAX_LIGHT_ID 时返回 None
+    pub fn single(id: usize) -> Option<Self> {
+        (id <= Self::MAX_LIGHT_ID).then(|| LightSet(1 << id))
+    }
+
+    pub fn union(&self, other: &LightSet) -> Self {
+        LightSet(self.0 | other.0)
+    }
+
+    /// 是否与 other 有公共光源
+    pub fn intersects(&self, other: &LightSet) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_rejects_ids_beyond_the_mask() {
+        assert_eq!(LightSet::single(0), Some(LightSet::ENVIRONMENT));
+        assert!(LightSet::single(LightSet::MAX_LIGHT_ID).is_some());
+        assert_eq!(LightSet::single(LightSet::MAX_LIGHT_ID + 1), None);
+        assert_eq!(LightSet::single(1000), None);
+    }
+}
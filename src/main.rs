@@ -1,94 +1,182 @@
-mod camera;
-mod hittable;
-mod interval;
-mod material;
-mod ray;
-mod sphere;
-mod vec3;
+use std::{env, fs, io, path::Path, process, str::FromStr};
 
-use std::rc::Rc;
+use ray_tracing::{
+    demo, distributed, preview, stats, AutoFocus, CancelToken, Crop, CropWindow, Point3, SceneFile,
+    Tile,
+};
 
-use camera::Camera;
-use hittable::HittableList;
-use material::{Dielectric, Lambertian, Material, Metal};
-use sphere::Sphere;
-use vec3::Vec3;
-
-fn main() {
-    // World
-    let mut world = HittableList::new();
-    let ground_material = Rc::new(Lambertian::new(Vec3::new(0.5, 0.5, 0.5)));
-    world.add(Rc::new(Sphere::new(
-        Vec3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        ground_material,
-    )));
-
-    for a in -11..11 {
-        for b in -11..11 {
-            let choose_mat = rand::random::<f64>();
-            let center = Vec3::new(
-                a as f64 + 0.9 * rand::random::<f64>(),
-                0.2,
-                b as f64 + 0.9 * rand::random::<f64>(),
-            );
-
-            if (&center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
-                let sphere_material: Rc<dyn Material>;
-
-                if choose_mat < 0.8 {
-                    // Diffuse
-                    let albedo = Vec3::random() * Vec3::random();
-                    sphere_material = Rc::new(Lambertian::new(albedo));
-                    world.add(Rc::new(Sphere::new(center, 0.2, sphere_material)));
-                } else if choose_mat < 0.95 {
-                    // metal
-                    let albedo = Vec3::random_in_range(0.5, 1.0); // 0.5 ~ 1.0
-                    let fuzz = rand::random::<f64>() * 0.5; // 0 ~ 0.5
-                    sphere_material = Rc::new(Metal::new(albedo, fuzz));
-                    world.add(Rc::new(Sphere::new(center, 0.2, sphere_material)));
-                } else {
-                    // Glass
-                    sphere_material = Rc::new(Dielectric::new(1.5));
-                    world.add(Rc::new(Sphere::new(center, 0.2, sphere_material)));
-                }
+/// 用法：`ray-tracing [选项] [场景文件]`，不给出场景文件时生成随机场景
+/// - `--save <路径>`：把要渲染的场景保存下来
+/// - `--stats`：渲染结束后把统计输出到标准错误
+/// - `--stats-json <路径>`：把统计以 JSON 格式写入文件
+/// - `--preview <地址>`：不输出 PPM，而在该地址（如 127.0.0.1:8080）提供浏览器交互预览，
+///   带访问令牌的页面地址打印到标准错误
+/// - `--coordinator <地址>`：在该地址等待工作进程连接，分发渲染任务并合并结果
+/// - `--job-samples <n>`：协调端每项任务的采样数，默认不按采样拆分
+/// - `--worker <地址>`：作为工作进程连接协调端，场景由协调端发送
+/// - `--crop <x,y,宽,高>`：只渲染该像素矩形
+/// - `--crop-normalized <x0,y0,x1,y1>`：只渲染按图像宽高归一化的该范围
+/// - `--full-frame`：裁剪时仍输出整幅图像，窗口外留黑
+/// - `--output <模板>`：渲染动画的各帧，第 n 帧写入把模板中的 # 替换为帧号的文件，
+///   已存在的帧跳过
+/// - `--frames <首帧>:<末帧>`：要渲染的帧，默认为动画的帧范围
+/// - `--autofocus <方式>`：自动对焦，方式为 `center`（画面中心的物体）、
+///   `point:<x,y,z>`（世界空间中的点）或 `object:<名称>`（场景中的节点）
+/// - `--focus-peaking <像素>`：把弥散圆直径小于该值的清晰区域标为绿色
+fn main() -> io::Result<()> {
+    let mut scene_path = None;
+    let mut save_path = None;
+    let mut print_stats = false;
+    let mut stats_path = None;
+    let mut preview_addr = None;
+    let mut coordinator_addr = None;
+    let mut job_samples = 0;
+    let mut worker_addr = None;
+    let mut crop_window = None;
+    let mut full_frame = false;
+    let mut frame_pattern = None;
+    let mut frames = None;
+    let mut autofocus = None;
+    let mut focus_peaking = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--save" => save_path = Some(args.next().unwrap_or_else(|| usage())),
+            "--stats" => print_stats = true,
+            "--stats-json" => stats_path = Some(args.next().unwrap_or_else(|| usage())),
+            "--preview" => preview_addr = Some(args.next().unwrap_or_else(|| usage())),
+            "--coordinator" => coordinator_addr = Some(args.next().unwrap_or_else(|| usage())),
+            "--job-samples" => {
+                job_samples = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or_else(|| usage())
+            }
+            "--worker" => worker_addr = Some(args.next().unwrap_or_else(|| usage())),
+            "--crop" => {
+                let [x, y, width, height] = parse_list(args.next()).unwrap_or_else(|| usage());
+                crop_window = Some(CropWindow::Pixels(Tile {
+                    x,
+                    y,
+                    width,
+                    height,
+                }));
+            }
+            "--crop-normalized" => {
+                let [x0, y0, x1, y1] = parse_list(args.next()).unwrap_or_else(|| usage());
+                crop_window = Some(CropWindow::Normalized { x0, y0, x1, y1 });
             }
+            "--full-frame" => full_frame = true,
+            "--output" => frame_pattern = Some(args.next().unwrap_or_else(|| usage())),
+            "--frames" => {
+                frames = args
+                    .next()
+                    .and_then(|range| {
+                        let (first, last) = range.split_once(':')?;
+                        Some(first.parse().ok()?..=last.parse().ok()?)
+                    })
+                    .or_else(|| usage())
+            }
+            "--autofocus" => {
+                autofocus = Some(parse_autofocus(args.next()).unwrap_or_else(|| usage()))
+            }
+            "--focus-peaking" => {
+                focus_peaking = Some(
+                    args.next()
+                        .and_then(|px| px.parse().ok())
+                        .unwrap_or_else(|| usage()),
+                )
+            }
+            _ if !arg.starts_with('-') && scene_path.is_none() => scene_path = Some(arg),
+            _ => usage(),
         }
     }
+    if let Some(addr) = worker_addr {
+        return distributed::work(addr.as_str());
+    }
+    stats::set_enabled(print_stats || stats_path.is_some());
 
-    let material1 = Rc::new(Dielectric::new(1.5));
-    world.add(Rc::new(Sphere::new(
-        Vec3::new(0.0, 1.0, 0.0),
-        1.0,
-        material1,
-    )));
+    let mut file = match scene_path {
+        Some(path) => SceneFile::load(Path::new(&path))?,
+        None => SceneFile::new(demo::camera(), demo::random_scene()),
+    };
+    if let Some(window) = crop_window {
+        file.camera.crop = Some(Crop { window, full_frame });
+    }
+    if autofocus.is_some() {
+        file.camera.autofocus = autofocus;
+    }
+    if focus_peaking.is_some() {
+        file.camera.focus_peaking = focus_peaking;
+    }
+    if let Some(path) = save_path {
+        file.save(Path::new(&path))?;
+    }
+    if let Some(pattern) = frame_pattern {
+        let frames = frames
+            .or_else(|| file.animation.as_ref().map(|a| a.start..=a.end))
+            .unwrap_or(0..=0);
+        file.render_frames(frames, &pattern, &CancelToken::new())?;
+    } else if let Some(addr) = coordinator_addr {
+        let image = distributed::coordinate(&mut file, addr.as_str(), job_samples, &mut |p| {
+            eprintln!("\r{p} ")
+        })?;
+        image.write_ppm(&mut io::stdout().lock())?;
+        eprintln!("\rDone.                 ");
+    } else {
+        let SceneFile {
+            mut camera, scene, ..
+        } = file;
+        let world = scene.build()?;
+        camera.auto_focus(&scene, &world)?;
+        match preview_addr {
+            Some(addr) => preview::serve(&mut camera, &scene, &world, addr.as_str())?,
+            None => camera.render(&world)?,
+        }
+    }
 
-    let material2 = Rc::new(Lambertian::new(Vec3::new(0.4, 0.2, 0.1)));
-    world.add(Rc::new(Sphere::new(
-        Vec3::new(-4.0, 1.0, 0.0),
-        1.0,
-        material2,
-    )));
+    let stats = stats::take();
+    if print_stats {
+        eprint!("{}", stats.report());
+    }
+    if let Some(path) = stats_path {
+        fs::write(path, stats.to_json())?;
+    }
+    Ok(())
+}
 
-    let material3 = Rc::new(Metal::new(Vec3::new(0.7, 0.6, 0.5), 0.0));
-    world.add(Rc::new(Sphere::new(
-        Vec3::new(4.0, 1.0, 0.0),
-        1.0,
-        material3,
-    )));
+/// 解析以逗号分隔的 N 个值
+fn parse_list<T: FromStr, const N: usize>(arg: Option<String>) -> Option<[T; N]> {
+    let values = arg?
+        .split(',')
+        .map(|v| v.trim().parse().ok())
+        .collect::<Option<Vec<T>>>()?;
+    values.try_into().ok()
+}
 
-    // Camera
-    let mut cam = Camera::default();
-    cam.aspect_ratio = 16.0 / 9.0;
-    cam.image_width = 1200;
-    cam.samples_per_pixel = 500;
-    cam.max_depth = 50;
-    cam.vfov = 20.0;
-    cam.lookfrom = Vec3::new(13.0, 2.0, 3.0);
-    cam.lookat = Vec3::new(0.0, 0.0, 0.0);
-    cam.vup = Vec3::new(0.0, 1.0, 0.0);
-    cam.defocus_angle = 0.6;
-    cam.focus_dist = 10.0;
+/// 解析自动对焦方式：center、point:x,y,z 或 object:名称
+fn parse_autofocus(arg: Option<String>) -> Option<AutoFocus> {
+    let arg = arg?;
+    match arg.split_once(':') {
+        None if arg == "center" => Some(AutoFocus::Center),
+        Some(("point", p)) => {
+            let [x, y, z] = parse_list(Some(p.to_string()))?;
+            Some(AutoFocus::Point(Point3::new(x, y, z)))
+        }
+        Some(("object", name)) if !name.is_empty() => Some(AutoFocus::Object(name.to_string())),
+        _ => None,
+    }
+}
 
-    cam.render(&world);
+fn usage() -> ! {
+    eprintln!(
+        "usage: ray-tracing [--save <scene.json>] [--stats] [--stats-json <stats.json>] \
+         [--preview <addr> | --coordinator <addr> [--job-samples <n>]] \
+         [--crop <x,y,w,h> | --crop-normalized <x0,y0,x1,y1>] [--full-frame] \
+         [--output <frame_####.ppm> [--frames <first>:<last>]] \
+         [--autofocus center|point:<x,y,z>|object:<name>] [--focus-peaking <px>] \
+         [<scene.json>]\n       \
+         ray-tracing --worker <addr>"
+    );
+    process::exit(2);
 }
@@ -1,51 +1,92 @@
-use crate::{hittable::HitRecord, ray::Ray, vec3::Vec3};
+use std::sync::Arc;
 
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hittable::HitRecord,
+    ray::{Ray, RayKind},
+    rng, stats,
+    texture::{SolidColor, Texture},
+    vec3::{consts::PI, Color, Real, Vec3},
+};
+
+/// 材质，序列化时以 type 字段区分具体类型
+#[typetag::serde(tag = "type")]
 pub trait Material: Sync {
     fn scatter(
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        attenuation: &mut Vec3,
+        attenuation: &mut Color,
         scattered: &mut Ray,
     ) -> bool;
+
+    /// 出射方向为 direction 时的 BRDF 乘以余弦项，以及 scatter 采样到该方向的概率密度，
+    /// 用于光源采样；镜面类材质无法对给定方向求值，返回 None
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Option<(Color, Real)> {
+        let _ = (r_in, rec, direction);
+        None
+    }
+
+    /// 交点处自身发出的辐亮度，默认不发光
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        let _ = rec;
+        Color::default()
+    }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Lambertian {
-    albedo: Vec3,
+    #[serde(with = "crate::library::reference")]
+    albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
-    pub fn new(albedo: Vec3) -> Self {
+    pub fn new(albedo: Color) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    /// 反照率随纹理变化的漫反射材质
+    pub fn from_texture(albedo: Arc<dyn Texture>) -> Self {
         Self { albedo }
     }
 }
 
+#[typetag::serde]
 impl Material for Lambertian {
     fn scatter(
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        attenuation: &mut Vec3,
+        attenuation: &mut Color,
         scattered: &mut Ray,
     ) -> bool {
         let _ = r_in;
-        let mut scatter_direction = &rec.normal + Vec3::random_unit_vector();
+        let mut scatter_direction = *rec.normal + Vec3::random_unit_vector();
         if scatter_direction.near_zero() {
-            scatter_direction = rec.normal.clone();
+            scatter_direction = *rec.normal;
         }
-        *scattered = Ray::new(rec.p.clone(), scatter_direction);
-        *attenuation = self.albedo.clone();
+        *scattered = rec.spawn_ray(scatter_direction, RayKind::Reflection);
+        *attenuation = self.albedo.value(rec);
         true
     }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: &Vec3) -> Option<(Color, Real)> {
+        let _ = r_in;
+        let cosine = rec.normal.dot(&direction.unit_vector()).max(0.0);
+        let albedo = self.albedo.value(rec);
+        Some((albedo * (cosine / PI), cosine / PI)) // 余弦加权采样
+    }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Metal {
-    albedo: Vec3,
-    fuzz: f64,
+    albedo: Color,
+    fuzz: Real,
 }
 
 impl Metal {
-    pub fn new(albedo: Vec3, fuzz: f64) -> Self {
+    pub fn new(albedo: Color, fuzz: Real) -> Self {
         if fuzz < 1.0 {
             Self { albedo, fuzz }
         } else {
@@ -54,49 +95,57 @@ impl Metal {
     }
 }
 
+#[typetag::serde]
 impl Material for Metal {
     fn scatter(
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        attenuation: &mut Vec3,
+        attenuation: &mut Color,
         scattered: &mut Ray,
     ) -> bool {
         let reflected = Vec3::reflect(&r_in.direction.unit_vector(), &rec.normal);
-        *scattered = Ray::new(
-            rec.p.clone(),
+        *scattered = rec.spawn_ray(
             reflected + self.fuzz * Vec3::random_unit_vector(),
+            RayKind::Reflection,
         );
-        *attenuation = self.albedo.clone();
-        scattered.direction.dot(&rec.normal) > 0.0
+        *attenuation = self.albedo;
+        if scattered.direction.dot(&rec.normal) <= 0.0 {
+            // 模糊后的反射方向位于表面以下，视为被吸收
+            stats::record(|s| s.metal_absorbed += 1);
+            return false;
+        }
+        true
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Dielectric {
-    ir: f64,
+    ir: Real,
 }
 
 impl Dielectric {
-    pub fn new(ir: f64) -> Self {
+    pub fn new(ir: Real) -> Self {
         Self { ir }
     }
 
     /// 反射率
-    fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
+    fn reflectance(cosine: Real, ref_idx: Real) -> Real {
         let r0 = ((1.0 - ref_idx) / (1.0 + ref_idx)).powi(2); // r0 = (1 - n1) / (1 + n1)
         r0 + (1.0 - r0) * (1.0 - cosine).powi(5) // r0 + (1 - r0)(1 - cos)^5
     }
 }
 
+#[typetag::serde]
 impl Material for Dielectric {
     fn scatter(
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        attenuation: &mut Vec3,
+        attenuation: &mut Color,
         scattered: &mut Ray,
     ) -> bool {
-        *attenuation = Vec3::new(1.0, 1.0, 1.0);
+        *attenuation = Color::new(1.0, 1.0, 1.0);
         let refraction_ratio = if rec.front_face {
             1.0 / self.ir
         } else {
@@ -109,14 +158,55 @@ impl Material for Dielectric {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
 
-        let direction =
-            if cannot_refract || Self::reflectance(cos_theta, refraction_ratio) > rand::random() {
-                Vec3::reflect(&unit_direction, &rec.normal)
+        *scattered =
+            if cannot_refract || Self::reflectance(cos_theta, refraction_ratio) > rng::random() {
+                rec.spawn_ray(
+                    Vec3::reflect(&unit_direction, &rec.normal),
+                    RayKind::Reflection,
+                )
             } else {
-                Vec3::refract(&unit_direction, &rec.normal, refraction_ratio)
+                rec.spawn_ray(
+                    Vec3::refract(&unit_direction, &rec.normal, refraction_ratio),
+                    RayKind::Refraction,
+                )
             };
-
-        *scattered = Ray::new(rec.p.clone(), direction);
         true
     }
 }
+
+/// 漫射光源，只从正面发光
+#[derive(Serialize, Deserialize)]
+pub struct DiffuseLight {
+    #[serde(with = "crate::library::reference")]
+    emit: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(emit)))
+    }
+
+    pub fn from_texture(emit: Arc<dyn Texture>) -> Self {
+        Self { emit }
+    }
+}
+
+#[typetag::serde]
+impl Material for DiffuseLight {
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        _rec: &HitRecord,
+        _attenuation: &mut Color,
+        _scattered: &mut Ray,
+    ) -> bool {
+        false
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        if !rec.front_face {
+            return Color::default();
+        }
+        self.emit.value(rec)
+    }
+}
@@ -0,0 +1,657 @@
+use overload::overload;
+use serde::{Deserialize, Serialize};
+use std::ops;
+
+use crate::{
+    ray::Ray,
+    vec3::{Normal3, Point3, Real, Vec3},
+};
+
+/// 行列式绝对值小于该值时视为奇异矩阵
+const SINGULAR_EPSILON: Real = 1e-12;
+
+/// 读取变换时 m 与 m_inv 之积偏离单位矩阵的相对容差
+const INVERSE_TOLERANCE: Real = 1e-4;
+
+/// 单次浮点运算的相对舍入误差上界
+pub const MACHINE_EPSILON: Real = Real::EPSILON * 0.5;
+
+/// n 次连续浮点运算累积的相对误差上界 γ(n)
+pub fn gamma(n: u32) -> Real {
+    let n = n as Real;
+    n * MACHINE_EPSILON / (1.0 - n * MACHINE_EPSILON)
+}
+
+/// 将交点 p 沿法线 n 推出其误差范围 p_error，推向 w 所在一侧，
+/// 使从该点出发的光线不会再次击中出发的表面
+pub fn offset_ray_origin(p: &Point3, p_error: &Vec3, n: &Vec3, w: &Vec3) -> Point3 {
+    let d = n.abs().dot(p_error);
+    let mut offset = d * n;
+    if w.dot(n) < 0.0 {
+        offset = -offset;
+    }
+    let mut po = p + offset;
+
+    // 加法本身也有舍入，再向偏移方向多走一个浮点数间隔
+    let round = |value: Real, offset: Real| {
+        if offset > 0.0 {
+            value.next_up()
+        } else if offset < 0.0 {
+            value.next_down()
+        } else {
+            value
+        }
+    };
+    po.0.x = round(po.x, offset.x);
+    po.0.y = round(po.y, offset.y);
+    po.0.z = round(po.z, offset.z);
+    po
+}
+
+/// 3×3 矩阵（行优先）
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat3 {
+    pub m: [[Real; 3]; 3],
+}
+
+impl Default for Mat3 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mat3 {
+    pub const IDENTITY: Mat3 = Mat3 {
+        m: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+    };
+
+    pub fn new(m: [[Real; 3]; 3]) -> Self {
+        Self { m }
+    }
+
+    /// 由三个列向量构造
+    pub fn from_cols(c0: &Vec3, c1: &Vec3, c2: &Vec3) -> Self {
+        Self::new([[c0.x, c1.x, c2.x], [c0.y, c1.y, c2.y], [c0.z, c1.z, c2.z]])
+    }
+
+    /// 缩放矩阵
+    pub fn scaling(s: &Vec3) -> Self {
+        Self::new([[s.x, 0.0, 0.0], [0.0, s.y, 0.0], [0.0, 0.0, s.z]])
+    }
+
+    /// 绕 x 轴旋转 angle 弧度
+    pub fn rotation_x(angle: Real) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new([[1.0, 0.0, 0.0], [0.0, cos, -sin], [0.0, sin, cos]])
+    }
+
+    /// 绕 y 轴旋转 angle 弧度
+    pub fn rotation_y(angle: Real) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new([[cos, 0.0, sin], [0.0, 1.0, 0.0], [-sin, 0.0, cos]])
+    }
+
+    /// 绕 z 轴旋转 angle 弧度
+    pub fn rotation_z(angle: Real) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new([[cos, -sin, 0.0], [sin, cos, 0.0], [0.0, 0.0, 1.0]])
+    }
+
+    /// 绕任意轴旋转 angle 弧度（Rodrigues 公式）
+    pub fn rotation(axis: &Vec3, angle: Real) -> Self {
+        let a = axis.unit_vector();
+        let (sin, cos) = angle.sin_cos();
+        let k = 1.0 - cos;
+        Self::new([
+            [
+                cos + a.x * a.x * k,
+                a.x * a.y * k - a.z * sin,
+                a.x * a.z * k + a.y * sin,
+            ],
+            [
+                a.y * a.x * k + a.z * sin,
+                cos + a.y * a.y * k,
+                a.y * a.z * k - a.x * sin,
+            ],
+            [
+                a.z * a.x * k - a.y * sin,
+                a.z * a.y * k + a.x * sin,
+                cos + a.z * a.z * k,
+            ],
+        ])
+    }
+
+    /// 第 i 行
+    pub fn row(&self, i: usize) -> Vec3 {
+        Vec3::new(self.m[i][0], self.m[i][1], self.m[i][2])
+    }
+
+    /// 第 j 列
+    pub fn col(&self, j: usize) -> Vec3 {
+        Vec3::new(self.m[0][j], self.m[1][j], self.m[2][j])
+    }
+
+    /// 转置
+    pub fn transpose(&self) -> Self {
+        Self::from_cols(&self.row(0), &self.row(1), &self.row(2))
+    }
+
+    /// 行列式
+    pub fn determinant(&self) -> Real {
+        self.row(0).dot(&self.row(1).cross(&self.row(2)))
+    }
+
+    /// 逆矩阵，奇异时返回 None
+    pub fn inverse(&self) -> Option<Self> {
+        let (r0, r1, r2) = (self.row(0), self.row(1), self.row(2));
+        let det = r0.dot(&r1.cross(&r2));
+        if det.abs() < SINGULAR_EPSILON {
+            return None;
+        }
+        // 逆矩阵的各列为两行叉积除以行列式
+        Some(Self::from_cols(&r1.cross(&r2), &r2.cross(&r0), &r0.cross(&r1)) * (1.0 / det))
+    }
+}
+
+overload!((a: ?Mat3) * (b: ?Mat3) -> Mat3 {
+    let mut m = [[0.0; 3]; 3];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = a.row(i).dot(&b.col(j));
+        }
+    }
+    Mat3 { m }
+});
+
+overload!((a: ?Mat3) * (b: ?Vec3) -> Vec3 {
+    let v = Vec3::new(b.x, b.y, b.z);
+    Vec3::new(a.row(0).dot(&v), a.row(1).dot(&v), a.row(2).dot(&v))
+});
+
+overload!((a: ?Mat3) * (b: ?Real) -> Mat3 {
+    Mat3 { m: a.m.map(|row| row.map(|v| v * b)) }
+});
+
+/// 4×4 齐次变换矩阵（行优先，作用于列向量）
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Mat4 {
+    pub m: [[Real; 4]; 4],
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4 {
+        m: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    pub fn new(m: [[Real; 4]; 4]) -> Self {
+        Self { m }
+    }
+
+    /// 由线性部分和平移构造仿射变换
+    pub fn from_affine(linear: &Mat3, translation: &Vec3) -> Self {
+        let l = &linear.m;
+        Self::new([
+            [l[0][0], l[0][1], l[0][2], translation.x],
+            [l[1][0], l[1][1], l[1][2], translation.y],
+            [l[2][0], l[2][1], l[2][2], translation.z],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// 平移矩阵
+    pub fn translation(t: &Vec3) -> Self {
+        Self::from_affine(&Mat3::IDENTITY, t)
+    }
+
+    /// 缩放矩阵
+    pub fn scaling(s: &Vec3) -> Self {
+        Self::from_affine(&Mat3::scaling(s), &Vec3::default())
+    }
+
+    /// 绕任意轴旋转 angle 弧度
+    pub fn rotation(axis: &Vec3, angle: Real) -> Self {
+        Self::from_affine(&Mat3::rotation(axis, angle), &Vec3::default())
+    }
+
+    /// 左上角 3×3 的线性部分
+    pub fn linear(&self) -> Mat3 {
+        let m = &self.m;
+        Mat3::new([
+            [m[0][0], m[0][1], m[0][2]],
+            [m[1][0], m[1][1], m[1][2]],
+            [m[2][0], m[2][1], m[2][2]],
+        ])
+    }
+
+    /// 转置
+    pub fn transpose(&self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Self { m }
+    }
+
+    /// 逆矩阵（列主元高斯-约当消元），奇异时返回 None
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Self::IDENTITY.m;
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+            if a[pivot][col].abs() < SINGULAR_EPSILON {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            a[col] = a[col].map(|v| v * scale);
+            inv[col] = inv[col].map(|v| v * scale);
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let factor = a[row][col];
+                for k in 0..4 {
+                    a[row][k] -= factor * a[col][k];
+                    inv[row][k] -= factor * inv[col][k];
+                }
+            }
+        }
+        Some(Self { m: inv })
+    }
+
+    /// 变换点（含平移与齐次除法）
+    pub fn transform_point(&self, p: &Point3) -> Point3 {
+        let m = &self.m;
+        let x = m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3];
+        let y = m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3];
+        let z = m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3];
+        let w = m[3][0] * p.x + m[3][1] * p.y + m[3][2] * p.z + m[3][3];
+        if w == 1.0 {
+            Point3::new(x, y, z)
+        } else {
+            Point3::new(x / w, y / w, z / w)
+        }
+    }
+
+    /// 变换向量（忽略平移）
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+}
+
+overload!((a: ?Mat4) * (b: ?Mat4) -> Mat4 {
+    let mut m = [[0.0; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a.m[i][k] * b.m[k][j]).sum();
+        }
+    }
+    Mat4 { m }
+});
+
+/// 可逆仿射变换，同时保存矩阵及其逆矩阵
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "TransformData")]
+pub struct Transform {
+    m: Mat4,
+    m_inv: Mat4,
+}
+
+/// 读取的变换，省略 m_inv 时由 m 求逆
+#[derive(Deserialize)]
+struct TransformData {
+    m: Mat4,
+    #[serde(default)]
+    m_inv: Option<Mat4>,
+}
+
+impl TryFrom<TransformData> for Transform {
+    type Error = String;
+
+    /// 拒绝奇异矩阵，以及与 m 不互逆的 m_inv
+    fn try_from(data: TransformData) -> Result<Self, String> {
+        let Some(m_inv) = data.m_inv else {
+            return Self::new(data.m).ok_or_else(|| "transform matrix is singular".to_string());
+        };
+        let product = data.m * m_inv;
+        for i in 0..4 {
+            for j in 0..4 {
+                // 按乘积各项的绝对值之和估计舍入误差
+                let bound: Real = (0..4).map(|k| (data.m.m[i][k] * m_inv.m[k][j]).abs()).sum();
+                let expected = if i == j { 1.0 } else { 0.0 };
+                if (product.m[i][j] - expected).abs() > INVERSE_TOLERANCE * bound.max(1.0) {
+                    return Err("m_inv is not the inverse of m".to_string());
+                }
+            }
+        }
+        Ok(Self { m: data.m, m_inv })
+    }
+}
+
+impl Transform {
+    /// 由矩阵创建，矩阵奇异时返回 None
+    pub fn new(m: Mat4) -> Option<Self> {
+        Some(Self {
+            m,
+            m_inv: m.inverse()?,
+        })
+    }
+
+    pub fn translate(t: &Vec3) -> Self {
+        Self {
+            m: Mat4::translation(t),
+            m_inv: Mat4::translation(&-t),
+        }
+    }
+
+    /// 缩放，各分量不能为 0
+    pub fn scale(s: &Vec3) -> Self {
+        Self {
+            m: Mat4::scaling(s),
+            m_inv: Mat4::scaling(&Vec3::new(1.0 / s.x, 1.0 / s.y, 1.0 / s.z)),
+        }
+    }
+
+    /// 绕任意轴旋转 angle 弧度
+    pub fn rotate(axis: &Vec3, angle: Real) -> Self {
+        let m = Mat4::rotation(axis, angle);
+        Self {
+            m,
+            m_inv: m.transpose(),
+        }
+    }
+
+    /// 由四元数表示的旋转
+    pub fn from_quat(q: &Quat) -> Self {
+        let m = Mat4::from_affine(&q.to_mat3(), &Vec3::default());
+        Self {
+            m,
+            m_inv: m.transpose(),
+        }
+    }
+
+    pub fn matrix(&self) -> &Mat4 {
+        &self.m
+    }
+
+    /// 逆变换
+    pub fn inverse(&self) -> Self {
+        Self {
+            m: self.m_inv,
+            m_inv: self.m,
+        }
+    }
+
+    pub fn point(&self, p: &Point3) -> Point3 {
+        self.m.transform_point(p)
+    }
+
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        self.m.transform_vector(v)
+    }
+
+    /// 变换点 p 并传播其绝对误差上界 p_error，返回新的点与误差上界
+    pub fn point_with_error(&self, p: &Point3, p_error: &Vec3) -> (Point3, Vec3) {
+        let error = |row: &[Real; 4]| {
+            (1.0 + gamma(3))
+                * (row[0].abs() * p_error.x + row[1].abs() * p_error.y + row[2].abs() * p_error.z)
+                + gamma(3)
+                    * ((row[0] * p.x).abs()
+                        + (row[1] * p.y).abs()
+                        + (row[2] * p.z).abs()
+                        + row[3].abs())
+        };
+        let m = &self.m.m;
+        (
+            self.point(p),
+            Vec3::new(error(&m[0]), error(&m[1]), error(&m[2])),
+        )
+    }
+
+    /// 变换法线：乘以逆矩阵的转置并重新归一化
+    pub fn normal(&self, n: &Normal3) -> Normal3 {
+        let m = &self.m_inv.m;
+        Normal3(
+            Vec3::new(
+                m[0][0] * n.x + m[1][0] * n.y + m[2][0] * n.z,
+                m[0][1] * n.x + m[1][1] * n.y + m[2][1] * n.z,
+                m[0][2] * n.x + m[1][2] * n.y + m[2][2] * n.z,
+            )
+            .unit_vector(),
+        )
+    }
+
+    /// 变换光线，方向不归一化，因此光线参数 t 在变换前后保持一致
+    pub fn ray(&self, r: &Ray) -> Ray {
+        Ray {
+            origin: self.point(&r.origin),
+            direction: self.vector(&r.direction),
+            ..*r
+        }
+    }
+}
+
+// 复合变换：(a * b) 先应用 b 再应用 a
+overload!((a: ?Transform) * (b: ?Transform) -> Transform {
+    Transform { m: a.m * b.m, m_inv: b.m_inv * a.m_inv }
+});
+
+/// 单位四元数表示的旋转
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quat {
+    pub v: Vec3, // 虚部
+    pub w: Real, // 实部
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Quat {
+    pub const IDENTITY: Quat = Quat {
+        v: Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        w: 1.0,
+    };
+
+    pub fn new(v: Vec3, w: Real) -> Self {
+        Self { v, w }
+    }
+
+    /// 绕 axis 旋转 angle 弧度
+    pub fn from_axis_angle(axis: &Vec3, angle: Real) -> Self {
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        Self::new(axis.unit_vector() * sin, cos)
+    }
+
+    pub fn dot(&self, rhs: &Quat) -> Real {
+        self.v.dot(&rhs.v) + self.w * rhs.w
+    }
+
+    pub fn length(&self) -> Real {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Self {
+        self * (1.0 / self.length())
+    }
+
+    /// 共轭，对单位四元数即逆旋转
+    pub fn conjugate(&self) -> Self {
+        Self::new(-self.v, self.w)
+    }
+
+    /// 旋转向量
+    pub fn rotate(&self, v: &Vec3) -> Vec3 {
+        let t = 2.0 * self.v.cross(v);
+        v + self.w * t + self.v.cross(&t)
+    }
+
+    /// 转换为旋转矩阵
+    pub fn to_mat3(self) -> Mat3 {
+        Mat3::from_cols(
+            &self.rotate(&Vec3::new(1.0, 0.0, 0.0)),
+            &self.rotate(&Vec3::new(0.0, 1.0, 0.0)),
+            &self.rotate(&Vec3::new(0.0, 0.0, 1.0)),
+        )
+    }
+
+    /// 球面线性插值，沿较短的弧从 a（t = 0）转到 b（t = 1）
+    pub fn slerp(a: &Quat, b: &Quat, t: Real) -> Quat {
+        let mut b = *b;
+        let mut cos_theta = a.dot(&b);
+        if cos_theta < 0.0 {
+            b = -b;
+            cos_theta = -cos_theta;
+        }
+        if cos_theta > 0.9995 {
+            // 夹角很小时退化为线性插值
+            return (a * (1.0 - t) + b * t).normalize();
+        }
+        let theta = cos_theta.clamp(-1.0, 1.0).acos();
+        let sin_theta = theta.sin();
+        a * (((1.0 - t) * theta).sin() / sin_theta) + b * ((t * theta).sin() / sin_theta)
+    }
+}
+
+overload!((a: ?Quat) + (b: ?Quat) -> Quat { Quat::new(a.v + b.v, a.w + b.w) });
+overload!((a: ?Quat) * (b: ?Real) -> Quat { Quat::new(a.v * b, a.w * b) });
+overload!(- (a: ?Quat) -> Quat { Quat::new(-a.v, -a.w) });
+// 四元数乘积：(a * b) 先应用 b 的旋转再应用 a 的旋转
+overload!((a: ?Quat) * (b: ?Quat) -> Quat {
+    Quat::new(a.w * b.v + b.w * a.v + a.v.cross(&b.v), a.w * b.w - a.v.dot(&b.v))
+});
+
+/// 标准正交基，w 为基准方向
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    /// 由单位向量 n 构造无分支的正交基（Duff et al. 2017）
+    pub fn new(n: &Vec3) -> Self {
+        let sign = (1.0 as Real).copysign(n.z);
+        let a = -1.0 / (sign + n.z);
+        let b = n.x * n.y * a;
+        Self {
+            u: Vec3::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x),
+            v: Vec3::new(b, sign + n.y * n.y * a, -n.y),
+            w: *n,
+        }
+    }
+
+    /// 以 w 为基准方向，u 取 up × w 方向，用于相机等需要确定朝上方向的场合
+    pub fn with_up(w: &Vec3, up: &Vec3) -> Self {
+        let w = w.unit_vector();
+        let u = up.cross(&w).unit_vector();
+        let v = w.cross(&u);
+        Self { u, v, w }
+    }
+
+    /// 局部坐标 -> 世界坐标
+    pub fn local(&self, a: &Vec3) -> Vec3 {
+        a.x * self.u + a.y * self.v + a.z * self.w
+    }
+
+    /// 世界坐标 -> 局部坐标
+    pub fn world_to_local(&self, a: &Vec3) -> Vec3 {
+        Vec3::new(a.dot(&self.u), a.dot(&self.v), a.dot(&self.w))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::consts::PI;
+
+    fn assert_close(a: &Vec3, b: &Vec3) {
+        assert!((a - b).length() < 1e-4, "{a:?} != {b:?}");
+    }
+
+    fn assert_identity(m: &Mat4) {
+        for (i, row) in m.m.iter().enumerate() {
+            for (j, &v) in row.iter().enumerate() {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((v - expected).abs() < 1e-4, "{m:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn inverse_undoes_the_matrix() {
+        let m = Mat4::translation(&Vec3::new(1.0, -2.0, 3.0))
+            * Mat4::rotation(&Vec3::new(1.0, 1.0, 0.0), 0.7)
+            * Mat4::scaling(&Vec3::new(2.0, 0.5, 3.0));
+        let inv = m.inverse().unwrap();
+        assert_identity(&(m * inv));
+        assert_identity(&(inv * m));
+
+        let m3 = m.linear();
+        let inv3 = m3.inverse().unwrap();
+        let v = Vec3::new(0.3, -1.2, 2.5);
+        assert_close(&(inv3 * (m3 * v)), &v);
+
+        assert!(Mat4::scaling(&Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+        assert!(Mat3::scaling(&Vec3::new(1.0, 1.0, 0.0)).inverse().is_none());
+    }
+
+    #[test]
+    fn transform_round_trips_points_and_keeps_normals_perpendicular() {
+        let t = Transform::translate(&Vec3::new(1.0, 2.0, 3.0))
+            * Transform::rotate(&Vec3::new(0.0, 1.0, 0.0), 0.5)
+            * Transform::scale(&Vec3::new(1.0, 4.0, 0.5));
+        let p = Point3::new(0.5, -1.0, 2.0);
+        assert_close(&t.inverse().point(&t.point(&p)), &p);
+
+        let tangent = Vec3::new(1.0, 1.0, 0.0);
+        let n = Normal3(Vec3::new(1.0, -1.0, 0.0).unit_vector());
+        assert!(t.vector(&tangent).dot(&t.normal(&n)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn slerp_interpolates_along_the_shorter_arc() {
+        let axis = Vec3::new(0.0, 0.0, 1.0);
+        let a = Quat::IDENTITY;
+        let b = Quat::from_axis_angle(&axis, PI / 2.0);
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        assert_close(&Quat::slerp(&a, &b, 0.0).rotate(&x), &x);
+        assert_close(&Quat::slerp(&a, &b, 1.0).rotate(&x), &b.rotate(&x));
+        let half = Quat::slerp(&a, &b, 0.5);
+        assert_close(&half.rotate(&x), &Vec3::new(0.5, 0.5, 0.0).unit_vector());
+        assert!((half.length() - 1.0).abs() < 1e-4);
+        // -b 表示同一旋转，插值仍沿较短的弧
+        assert_close(&Quat::slerp(&a, &-b, 0.5).rotate(&x), &half.rotate(&x));
+        // 夹角很小时退化为线性插值
+        let c = Quat::from_axis_angle(&axis, 1e-3);
+        assert!((Quat::slerp(&a, &c, 0.5).length() - 1.0).abs() < 1e-4);
+    }
+}
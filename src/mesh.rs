@@ -0,0 +1,340 @@
+use std::{io, path::Path, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aabb::Aabb,
+    bvh::Bvh,
+    hittable::{HitRecord, Hittable, HittableList, Shape, SurfaceInteraction},
+    image::invalid_data,
+    interval::Interval,
+    material::Material,
+    packet::{record_hits, PacketRecords, RayPacket, PACKET_WIDTH},
+    ray::Ray,
+    simd::Mask,
+    triangle::Triangle,
+    vec3::{Color, Normal3, Point3, Real, Vec3},
+};
+
+/// 三角形网格的顶点数据，法线与纹理坐标为空时表示没有该属性
+#[derive(Default, Serialize, Deserialize)]
+#[serde(try_from = "MeshData")]
+pub struct Mesh {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,       // 顶点法线
+    pub uvs: Vec<(Real, Real)>,   // 顶点纹理坐标，v 轴向上
+    pub colors: Vec<Color>,       // 顶点颜色（线性）
+    pub indices: Vec<[usize; 3]>, // 每个三角形的顶点下标，逆时针为正面
+}
+
+#[derive(Deserialize)]
+struct MeshData {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(Real, Real)>,
+    colors: Vec<Color>,
+    indices: Vec<[usize; 3]>,
+}
+
+impl TryFrom<MeshData> for Mesh {
+    type Error = io::Error;
+
+    /// 拒绝不满足 Mesh::validate 的网格数据
+    fn try_from(data: MeshData) -> io::Result<Self> {
+        let mesh = Self {
+            positions: data.positions,
+            normals: data.normals,
+            uvs: data.uvs,
+            colors: data.colors,
+            indices: data.indices,
+        };
+        mesh.validate()?;
+        Ok(mesh)
+    }
+}
+
+impl Mesh {
+    /// 按扩展名读取网格：.ply 或 .stl
+    pub fn load(path: &Path) -> io::Result<Mesh> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("ply") => Self::load_ply(path),
+            Some("stl") => Self::load_stl(path),
+            _ => Err(invalid_data(format!(
+                "{}: unsupported mesh format",
+                path.display()
+            ))),
+        }
+    }
+
+    /// 检查顶点下标均小于顶点数，且非空的法线、纹理坐标与颜色与顶点一一对应
+    pub fn validate(&self) -> io::Result<()> {
+        let count = self.positions.len();
+        for (name, len) in [
+            ("normals", self.normals.len()),
+            ("uvs", self.uvs.len()),
+            ("colors", self.colors.len()),
+        ] {
+            if len != 0 && len != count {
+                return Err(invalid_data(format!(
+                    "mesh has {count} positions but {len} {name}"
+                )));
+            }
+        }
+        for (face, indices) in self.indices.iter().enumerate() {
+            if let Some(index) = indices.iter().find(|&&i| i >= count) {
+                return Err(invalid_data(format!(
+                    "mesh face {face} has vertex index {index} but only {count} positions"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// 以 material 构建网格的所有三角形及其 BVH；退化三角形被跳过。
+    /// 网格需满足 validate，否则越界的下标会导致 panic
+    pub fn build(self, material: Rc<dyn Material>) -> TriangleMesh {
+        let mesh = Rc::new(self);
+        let mut list = HittableList::new();
+        for (face, [i0, i1, i2]) in mesh.indices.iter().copied().enumerate() {
+            let (p0, p1, p2) = (mesh.positions[i0], mesh.positions[i1], mesh.positions[i2]);
+            if (p1 - p0).cross(&(p2 - p0)).near_zero() {
+                continue;
+            }
+            list.add(Rc::new(MeshTriangle {
+                triangle: Triangle::new(p0, p1, p2, material.clone()),
+                mesh: mesh.clone(),
+                face,
+            }));
+        }
+        TriangleMesh {
+            bvh: Bvh::new(list),
+            mesh,
+            material,
+        }
+    }
+
+    /// 三角形 face 的三个顶点下标及重心坐标 (b1, b2) 对应的插值权重
+    fn weights(&self, face: usize, (b1, b2): (Real, Real)) -> [(usize, Real); 3] {
+        let [i0, i1, i2] = self.indices[face];
+        [(i0, 1.0 - b1 - b2), (i1, b1), (i2, b2)]
+    }
+}
+
+/// 以同一材质构建的三角形网格；只序列化网格数据与材质，读取时重新构建三角形
+#[derive(Serialize, Deserialize)]
+#[serde(from = "TriangleMeshData")]
+pub struct TriangleMesh {
+    mesh: Rc<Mesh>,
+    #[serde(with = "crate::library::reference")]
+    material: Rc<dyn Material>,
+    #[serde(skip_serializing)]
+    bvh: Bvh, // 网格三角形的 BVH
+}
+
+#[derive(Deserialize)]
+struct TriangleMeshData {
+    mesh: Mesh,
+    #[serde(with = "crate::library::reference")]
+    material: Rc<dyn Material>,
+}
+
+impl From<TriangleMeshData> for TriangleMesh {
+    fn from(data: TriangleMeshData) -> Self {
+        data.mesh.build(data.material)
+    }
+}
+
+#[typetag::serde]
+impl Hittable for TriangleMesh {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<SurfaceInteraction<'_>> {
+        self.bvh.hit(r, ray_t)
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.bvh.occluded(r, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+
+    fn hit_packet<'a>(
+        &'a self,
+        packet: &mut RayPacket<PACKET_WIDTH>,
+        recs: &mut PacketRecords<'a>,
+    ) -> Mask<PACKET_WIDTH> {
+        self.bvh.hit_packet(packet, recs)
+    }
+
+    fn occluded_packet(&self, packet: &RayPacket<PACKET_WIDTH>) -> Mask<PACKET_WIDTH> {
+        self.bvh.occluded_packet(packet)
+    }
+}
+
+/// 网格中的一个三角形，着色时插值顶点法线、纹理坐标与颜色
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "MeshTriangleData")]
+struct MeshTriangle {
+    triangle: Triangle,
+    mesh: Rc<Mesh>,
+    face: usize, // 在网格中的序号
+}
+
+#[derive(Deserialize)]
+struct MeshTriangleData {
+    triangle: Triangle,
+    mesh: Rc<Mesh>,
+    face: usize,
+}
+
+impl TryFrom<MeshTriangleData> for MeshTriangle {
+    type Error = io::Error;
+
+    /// 拒绝超出网格三角形数的序号
+    fn try_from(data: MeshTriangleData) -> io::Result<Self> {
+        if data.face >= data.mesh.indices.len() {
+            return Err(invalid_data(format!(
+                "mesh triangle {} out of range for a mesh of {} triangles",
+                data.face,
+                data.mesh.indices.len()
+            )));
+        }
+        Ok(Self {
+            triangle: data.triangle,
+            mesh: data.mesh,
+            face: data.face,
+        })
+    }
+}
+
+impl Shape for MeshTriangle {
+    fn surface<'a>(&self, r: &Ray, si: &SurfaceInteraction<'a>) -> HitRecord<'a> {
+        let mut rec = self.triangle.surface(r, si);
+        let weights = self.mesh.weights(self.face, si.uv);
+        if !self.mesh.normals.is_empty() {
+            let n = weights
+                .iter()
+                .fold(Vec3::default(), |n, &(i, w)| n + self.mesh.normals[i] * w)
+                .unit_vector();
+            // 着色法线翻到几何法线所在一侧，再按光线方向取朝向
+            let n = if n.dot(&self.triangle.normal) < 0.0 {
+                -n
+            } else {
+                n
+            };
+            rec.normal = Normal3(if rec.front_face { n } else { -n });
+        }
+        if !self.mesh.uvs.is_empty() {
+            (rec.u, rec.v) = weights.iter().fold((0.0, 0.0), |(u, v), &(i, w)| {
+                let (ui, vi) = self.mesh.uvs[i];
+                (u + ui * w, v + vi * w)
+            });
+        }
+        if !self.mesh.colors.is_empty() {
+            rec.color = weights
+                .iter()
+                .fold(Color::default(), |c, &(i, w)| c + self.mesh.colors[i] * w);
+        }
+        rec
+    }
+}
+
+#[typetag::serde]
+impl Hittable for MeshTriangle {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<SurfaceInteraction<'_>> {
+        let (t, u, v) = self.triangle.intersect(r, ray_t)?;
+        Some(SurfaceInteraction::new(
+            t,
+            (u, v),
+            self.triangle.material.as_ref(),
+            self,
+        ))
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.triangle.occluded(r, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.triangle.bounding_box()
+    }
+
+    fn hit_packet<'a>(
+        &'a self,
+        packet: &mut RayPacket<PACKET_WIDTH>,
+        recs: &mut PacketRecords<'a>,
+    ) -> Mask<PACKET_WIDTH> {
+        let (hits, t, u, v) = self.triangle.intersect_packet(packet);
+        record_hits(packet, recs, hits, |i| {
+            SurfaceInteraction::new(
+                t.0[i],
+                (u.0[i], v.0[i]),
+                self.triangle.material.as_ref(),
+                self,
+            )
+        });
+        hits
+    }
+
+    fn occluded_packet(&self, packet: &RayPacket<PACKET_WIDTH>) -> Mask<PACKET_WIDTH> {
+        self.triangle.occluded_packet(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::Camera,
+        material::Lambertian,
+        scene::{Node, Scene, SceneFile},
+    };
+
+    /// 含一个三角形网格的场景文件
+    fn scene_json(mesh: Mesh) -> String {
+        let mut scene = Scene::default();
+        let material: Rc<dyn Material> = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        scene.add(Node::object("mesh", Rc::new(mesh.build(material))));
+        SceneFile::new(Camera::default(), scene).to_json()
+    }
+
+    fn triangle() -> Mesh {
+        Mesh {
+            positions: vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            indices: vec![[0, 1, 2]],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn valid_mesh_round_trips() {
+        let json = scene_json(triangle());
+        assert!(SceneFile::from_json(&json).is_ok());
+    }
+
+    #[test]
+    fn out_of_range_index_is_an_error() {
+        let mut value: serde_json::Value = serde_json::from_str(&scene_json(triangle())).unwrap();
+        value["scene"]["root"]["children"][0]["object"]["mesh"]["indices"][0][2] = 7.into();
+        let err = SceneFile::from_json(&value.to_string()).err().unwrap();
+        assert!(err.to_string().contains("vertex index 7"), "{err}");
+    }
+
+    #[test]
+    fn mismatched_attribute_count_is_an_error() {
+        let mut mesh = triangle();
+        mesh.normals = vec![Vec3::new(0.0, 0.0, 1.0)];
+        assert!(mesh.validate().is_err());
+        mesh.normals.clear();
+        mesh.colors = vec![Color::new(1.0, 0.0, 0.0); 2];
+        assert!(mesh.validate().is_err());
+    }
+}
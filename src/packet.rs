@@ -0,0 +1,311 @@
+use std::ops;
+
+use crate::{
+    hittable::{Hittable, SurfaceInteraction},
+    interval::Interval,
+    ray::{Ray, RayKind},
+    simd::{Lanes, Mask},
+    vec3::{Point3, Real, Vec3},
+};
+
+/// 光线包宽度（4 或 8）
+pub const PACKET_WIDTH: usize = 8;
+
+/// 进入某个 BVH 节点或物体包围盒的活跃光线数不超过该值时，视为光线包已发散，
+/// 对该节点或物体退回逐条求交
+pub const DIVERGENCE_THRESHOLD: usize = 2;
+
+/// 光线包中每条光线的最近交点
+pub type PacketRecords<'a> = [Option<SurfaceInteraction<'a>>; PACKET_WIDTH];
+
+/// SoA 布局的一组三维向量，批量的点积、叉积与最值按通道计算（耗时比较见 benches/simd.rs）
+#[derive(Clone, Copy, Debug)]
+pub struct Vec3Lanes<const N: usize> {
+    pub x: Lanes<N>,
+    pub y: Lanes<N>,
+    pub z: Lanes<N>,
+}
+
+impl<const N: usize> Vec3Lanes<N> {
+    /// 所有通道取同一向量
+    pub fn splat(v: &Vec3) -> Self {
+        Self {
+            x: Lanes::splat(v.x),
+            y: Lanes::splat(v.y),
+            z: Lanes::splat(v.z),
+        }
+    }
+
+    /// 逐通道点积
+    #[inline(always)]
+    pub fn dot(&self, rhs: &Self) -> Lanes<N> {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    /// 逐通道叉积
+    #[inline(always)]
+    pub fn cross(&self, rhs: &Self) -> Self {
+        Self {
+            x: self.y * rhs.z - self.z * rhs.y,
+            y: self.z * rhs.x - self.x * rhs.z,
+            z: self.x * rhs.y - self.y * rhs.x,
+        }
+    }
+
+    /// 逐通道、逐分量最小值
+    #[inline(always)]
+    pub fn min(&self, rhs: &Self) -> Self {
+        Self {
+            x: self.x.min(rhs.x),
+            y: self.y.min(rhs.y),
+            z: self.z.min(rhs.z),
+        }
+    }
+
+    /// 逐通道、逐分量最大值
+    #[inline(always)]
+    pub fn max(&self, rhs: &Self) -> Self {
+        Self {
+            x: self.x.max(rhs.x),
+            y: self.y.max(rhs.y),
+            z: self.z.max(rhs.z),
+        }
+    }
+
+    /// 第 i 个通道的向量
+    pub fn get(&self, i: usize) -> Vec3 {
+        Vec3::new(self.x.0[i], self.y.0[i], self.z.0[i])
+    }
+
+    /// 设置第 i 个通道的向量
+    pub fn set(&mut self, i: usize, v: &Vec3) {
+        self.x.0[i] = v.x;
+        self.y.0[i] = v.y;
+        self.z.0[i] = v.z;
+    }
+}
+
+impl<const N: usize> ops::Sub for Vec3Lanes<N> {
+    type Output = Self;
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+/// SoA 布局的光线包，所有光线共享 t_min 与光线类型，t_max 随求交逐条收缩
+#[derive(Clone, Copy)]
+pub struct RayPacket<const N: usize> {
+    pub origin: Vec3Lanes<N>,
+    pub direction: Vec3Lanes<N>,
+    pub t_min: Real,
+    pub t_max: Lanes<N>, // 各光线当前的最近交点距离
+    pub active: Mask<N>, // 参与求交的光线
+    pub kind: RayKind,   // 光线类型
+}
+
+impl<const N: usize> RayPacket<N> {
+    /// 由若干条光线组成光线包，None 对应的通道不参与求交
+    pub fn new(rays: &[Option<Ray>; N], ray_t: Interval) -> Self {
+        let zero = Vec3::default();
+        let mut packet = Self {
+            origin: Vec3Lanes::splat(&zero),
+            direction: Vec3Lanes::splat(&zero),
+            t_min: ray_t.min,
+            t_max: Lanes::splat(ray_t.max),
+            active: Mask([false; N]),
+            kind: RayKind::default(),
+        };
+        for (i, ray) in rays.iter().enumerate() {
+            if let Some(r) = ray {
+                packet.origin.set(i, &r.origin);
+                packet.direction.set(i, &r.direction);
+                packet.active.0[i] = true;
+                packet.kind = r.kind;
+            }
+        }
+        packet
+    }
+
+    /// 第 i 条光线
+    pub fn ray(&self, i: usize) -> Ray {
+        Ray::new(Point3(self.origin.get(i)), self.direction.get(i), self.kind)
+    }
+
+    /// 第 i 条光线当前的求交区间
+    pub fn interval(&self, i: usize) -> Interval {
+        Interval::new(self.t_min, self.t_max.0[i])
+    }
+
+    /// 各光线方向的倒数，用于包围盒求交
+    pub fn inv_direction(&self) -> Vec3Lanes<N> {
+        let one = Lanes::splat(1.0);
+        Vec3Lanes {
+            x: one / self.direction.x,
+            y: one / self.direction.y,
+            z: one / self.direction.z,
+        }
+    }
+
+    /// 整个光线包的活跃光线是否已少到应退回逐条求交
+    pub fn diverged(&self) -> bool {
+        self.active.count() <= DIVERGENCE_THRESHOLD
+    }
+}
+
+/// 对光线包中的活跃光线逐条调用 hit，作为默认实现及发散后的回退路径
+pub fn hit_each<'a, H: Hittable + ?Sized>(
+    object: &'a H,
+    packet: &mut RayPacket<PACKET_WIDTH>,
+    recs: &mut PacketRecords<'a>,
+) -> Mask<PACKET_WIDTH> {
+    let lanes = packet.active;
+    hit_lanes(lanes, packet, recs, |r, ray_t| object.hit(r, ray_t))
+}
+
+/// 对光线包中的活跃光线逐条调用 occluded，作为默认实现及发散后的回退路径
+pub fn occluded_each<H: Hittable + ?Sized>(
+    object: &H,
+    packet: &RayPacket<PACKET_WIDTH>,
+) -> Mask<PACKET_WIDTH> {
+    let mut occluded = Mask([false; PACKET_WIDTH]);
+    for (i, o) in occluded.0.iter_mut().enumerate() {
+        *o = packet.active.0[i] && object.occluded(&packet.ray(i), packet.interval(i));
+    }
+    occluded
+}
+
+/// 对 lanes 中的光线逐条调用 hit 求交，击中时收缩 t_max 并写入交点
+pub fn hit_lanes<'a>(
+    lanes: Mask<PACKET_WIDTH>,
+    packet: &mut RayPacket<PACKET_WIDTH>,
+    recs: &mut PacketRecords<'a>,
+    hit: impl Fn(&Ray, Interval) -> Option<SurfaceInteraction<'a>>,
+) -> Mask<PACKET_WIDTH> {
+    let mut hits = Mask([false; PACKET_WIDTH]);
+    for (i, slot) in recs.iter_mut().enumerate() {
+        if !lanes.0[i] {
+            continue;
+        }
+        if let Some(si) = hit(&packet.ray(i), packet.interval(i)) {
+            packet.t_max.0[i] = si.t;
+            *slot = Some(si);
+            hits.0[i] = true;
+        }
+    }
+    hits
+}
+
+/// 为 hits 中的光线写入交点并收缩 t_max，interaction 由通道号给出该光线的交点
+pub fn record_hits<'a>(
+    packet: &mut RayPacket<PACKET_WIDTH>,
+    recs: &mut PacketRecords<'a>,
+    hits: Mask<PACKET_WIDTH>,
+    interaction: impl Fn(usize) -> SurfaceInteraction<'a>,
+) {
+    for (i, slot) in recs.iter_mut().enumerate() {
+        if hits.0[i] {
+            let si = interaction(i);
+            packet.t_max.0[i] = si.t;
+            *slot = Some(si);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::{
+        bvh::Bvh,
+        hittable::HittableList,
+        material::{Lambertian, Material},
+        sphere::Sphere,
+        triangle::Triangle,
+        vec3::Color,
+    };
+
+    /// 随机分布的球与三角形组成的 BVH
+    fn world() -> Bvh {
+        let material: Rc<dyn Material> = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let mut list = HittableList::new();
+        for _ in 0..60 {
+            let center = Point3(Vec3::random_in_range(-5.0, 5.0));
+            list.add(Rc::new(Sphere::new(center, 0.3, material.clone())));
+            let [a, b] = [0; 2].map(|_| Vec3::random_in_range(-1.0, 1.0));
+            list.add(Rc::new(Triangle::new(
+                center + 2.0 * a,
+                center + 2.0 * b,
+                center + a.cross(&b),
+                material.clone(),
+            )));
+        }
+        Bvh::new(list)
+    }
+
+    /// 光线包的求交与遮挡结果与逐条光线相同，返回击中的光线数
+    fn assert_matches_single_rays(world: &Bvh, rays: &[Option<Ray>; PACKET_WIDTH]) -> usize {
+        let ray_t = Interval::new(0.001, Real::INFINITY);
+        let mut packet = RayPacket::new(rays, ray_t);
+        let occluded = world.occluded_packet(&packet);
+        let mut recs: PacketRecords = std::array::from_fn(|_| None);
+        let hits = world.hit_packet(&mut packet, &mut recs);
+        for (i, ray) in rays.iter().enumerate() {
+            let Some(r) = ray else {
+                assert!(!hits.0[i] && !occluded.0[i] && recs[i].is_none());
+                continue;
+            };
+            let expected = world.hit(r, ray_t).map(|si| si.t);
+            let t = recs[i].as_ref().map(|si| si.t);
+            assert_eq!(hits.0[i], expected.is_some());
+            assert_eq!(occluded.0[i], expected.is_some());
+            match (t, expected) {
+                (Some(t), Some(expected)) => {
+                    assert!((t - expected).abs() <= 1e-4 * expected, "{t} != {expected}")
+                }
+                (t, expected) => assert_eq!(t, expected),
+            }
+        }
+        hits.count()
+    }
+
+    #[test]
+    fn coherent_packets_match_single_rays() {
+        let world = world();
+        let origin = Point3::new(0.0, 0.0, 12.0);
+        let mut hits = 0;
+        for _ in 0..200 {
+            // 指向相邻像素的一组光线，其中一条不参与求交
+            let target = Point3(Vec3::random_in_range(-5.0, 5.0));
+            let mut rays: [Option<Ray>; PACKET_WIDTH] = std::array::from_fn(|i| {
+                let offset = Vec3::new(i as Real * 0.02, 0.0, 0.0);
+                Some(Ray::new(origin, target + offset - origin, RayKind::Camera))
+            });
+            rays[3] = None;
+            hits += assert_matches_single_rays(&world, &rays);
+        }
+        assert!(hits > 0);
+    }
+
+    #[test]
+    fn divergent_packets_match_single_rays() {
+        let world = world();
+        let mut hits = 0;
+        for _ in 0..200 {
+            let rays = std::array::from_fn(|_| {
+                Some(Ray::new(
+                    Point3(Vec3::random_in_range(-8.0, 8.0)),
+                    Vec3::random_unit_vector(),
+                    RayKind::Shadow,
+                ))
+            });
+            hits += assert_matches_single_rays(&world, &rays);
+        }
+        assert!(hits > 0);
+    }
+}
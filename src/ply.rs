@@ -0,0 +1,349 @@
+use std::{fs, io, path::Path};
+
+use crate::{
+    image::{invalid_data, srgb_to_linear},
+    mesh::Mesh,
+    vec3::{Color, Point3, Real, Vec3},
+};
+
+/// PLY 数据的编码方式
+#[derive(Clone, Copy, PartialEq)]
+enum Encoding {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// PLY 标量类型
+#[derive(Clone, Copy, PartialEq)]
+enum Scalar {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> io::Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Self::Int8,
+            "uchar" | "uint8" => Self::UInt8,
+            "short" | "int16" => Self::Int16,
+            "ushort" | "uint16" => Self::UInt16,
+            "int" | "int32" => Self::Int32,
+            "uint" | "uint32" => Self::UInt32,
+            "float" | "float32" => Self::Float32,
+            "double" | "float64" => Self::Float64,
+            _ => return Err(invalid_data(format!("unknown PLY type {name}"))),
+        })
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Self::Int8 | Self::UInt8 => 1,
+            Self::Int16 | Self::UInt16 => 2,
+            Self::Int32 | Self::UInt32 | Self::Float32 => 4,
+            Self::Float64 => 8,
+        }
+    }
+}
+
+/// 元素的一个属性：标量，或以 count 类型给出长度的列表
+struct Property {
+    name: String,
+    scalar: Scalar,
+    count: Option<Scalar>,
+}
+
+/// 头部声明的元素（vertex、face 等）
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// 按编码读取数据部分的游标
+struct Body<'a> {
+    data: &'a [u8],
+    pos: usize,
+    encoding: Encoding,
+}
+
+impl Body<'_> {
+    /// 读取一个值，以 f64 返回以免大下标在单精度下丢失精度
+    fn read(&mut self, scalar: Scalar) -> io::Result<f64> {
+        if self.encoding == Encoding::Ascii {
+            return self.read_ascii();
+        }
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + scalar.size())
+            .ok_or_else(|| invalid_data("truncated PLY data".to_string()))?;
+        self.pos += scalar.size();
+        let mut buf = [0u8; 8];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        if self.encoding == Encoding::BinaryBigEndian {
+            buf[..bytes.len()].reverse();
+        }
+        Ok(match scalar {
+            Scalar::Int8 => buf[0] as i8 as f64,
+            Scalar::UInt8 => buf[0] as f64,
+            Scalar::Int16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+            Scalar::UInt16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+            Scalar::Int32 => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            Scalar::UInt32 => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            Scalar::Float32 => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            Scalar::Float64 => f64::from_le_bytes(buf),
+        })
+    }
+
+    fn read_ascii(&mut self) -> io::Result<f64> {
+        while self.pos < self.data.len() && self.data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        while self.pos < self.data.len() && !self.data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        let token = String::from_utf8_lossy(&self.data[start..self.pos]);
+        token
+            .parse()
+            .map_err(|_| invalid_data(format!("invalid PLY value {token:?}")))
+    }
+}
+
+impl Mesh {
+    /// 读取 PLY 网格（ASCII 或二进制），支持顶点法线、纹理坐标与颜色，多边形面按扇形三角化；
+    /// 8 位与 16 位整数颜色按 sRGB 编码处理。只有顶点而没有面的点云不受支持，返回错误
+    pub fn load_ply(path: &Path) -> io::Result<Mesh> {
+        let data = fs::read(path)?;
+        let mut pos = 0;
+        let (encoding, elements) = parse_header(&data, &mut pos)?;
+        let mut body = Body {
+            data: &data,
+            pos,
+            encoding,
+        };
+
+        let mut mesh = Mesh::default();
+        for element in elements.iter() {
+            let index = |names: &[&str]| {
+                element
+                    .properties
+                    .iter()
+                    .position(|p| names.contains(&p.name.as_str()))
+            };
+            let position = [index(&["x"]), index(&["y"]), index(&["z"])];
+            let normal = [index(&["nx"]), index(&["ny"]), index(&["nz"])];
+            let uv = [
+                index(&["u", "s", "texture_u", "texture_s"]),
+                index(&["v", "t", "texture_v", "texture_t"]),
+            ];
+            let color = [
+                index(&["red", "r", "diffuse_red"]),
+                index(&["green", "g", "diffuse_green"]),
+                index(&["blue", "b", "diffuse_blue"]),
+            ];
+            let vertex_indices = index(&["vertex_indices", "vertex_index"]);
+            let color_scale = color[0].and_then(|i| match element.properties[i].scalar {
+                Scalar::UInt8 => Some(255.0),
+                Scalar::UInt16 => Some(65535.0),
+                _ => None,
+            });
+
+            let mut values = Vec::new();
+            for _ in 0..element.count {
+                values.clear();
+                let mut list = Vec::new();
+                for (i, property) in element.properties.iter().enumerate() {
+                    match property.count {
+                        Some(count) => {
+                            let n = to_index(body.read(count)?)?;
+                            let items = (0..n)
+                                .map(|_| body.read(property.scalar))
+                                .collect::<io::Result<Vec<_>>>()?;
+                            if Some(i) == vertex_indices {
+                                list = items;
+                            }
+                            values.push(0.0);
+                        }
+                        None => values.push(body.read(property.scalar)?),
+                    }
+                }
+
+                match element.name.as_str() {
+                    "vertex" => {
+                        let [Some(x), Some(y), Some(z)] = position else {
+                            return Err(invalid_data("PLY vertex without x/y/z".to_string()));
+                        };
+                        let value = |i: usize| values[i] as Real;
+                        mesh.positions
+                            .push(Point3::new(value(x), value(y), value(z)));
+                        if let [Some(x), Some(y), Some(z)] = normal {
+                            mesh.normals.push(Vec3::new(value(x), value(y), value(z)));
+                        }
+                        if let [Some(u), Some(v)] = uv {
+                            mesh.uvs.push((value(u), value(v)));
+                        }
+                        if let [Some(r), Some(g), Some(b)] = color {
+                            let c = |i: usize| match color_scale {
+                                Some(scale) => srgb_to_linear(value(i) / scale),
+                                None => value(i),
+                            };
+                            mesh.colors.push(Color::new(c(r), c(g), c(b)));
+                        }
+                    }
+                    "face" => {
+                        let face = list
+                            .iter()
+                            .map(|&i| to_index(i))
+                            .collect::<io::Result<Vec<_>>>()?;
+                        for k in 1..face.len().saturating_sub(1) {
+                            mesh.indices.push([face[0], face[k], face[k + 1]]);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if mesh.indices.is_empty() {
+            return Err(invalid_data(format!(
+                "{}: PLY file contains no faces (point clouds are not supported)",
+                path.display()
+            )));
+        }
+        mesh.validate()
+            .map_err(|e| invalid_data(format!("{}: {e}", path.display())))?;
+        Ok(mesh)
+    }
+}
+
+/// 把读到的列表长度或顶点下标转换为 usize，拒绝负数与非整数
+fn to_index(value: f64) -> io::Result<usize> {
+    if value.fract() != 0.0 {
+        return Err(invalid_data(format!("invalid PLY index {value}")));
+    }
+    usize::try_from(value as i64).map_err(|_| invalid_data(format!("invalid PLY index {value}")))
+}
+
+/// 解析 PLY 头部，pos 移动到数据部分的开头
+fn parse_header(data: &[u8], pos: &mut usize) -> io::Result<(Encoding, Vec<Element>)> {
+    if read_line(data, pos)? != "ply" {
+        return Err(invalid_data("missing PLY magic".to_string()));
+    }
+
+    let mut encoding = None;
+    let mut elements: Vec<Element> = Vec::new();
+    loop {
+        let line = read_line(data, pos)?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", format, _version] => {
+                encoding = Some(match *format {
+                    "ascii" => Encoding::Ascii,
+                    "binary_little_endian" => Encoding::BinaryLittleEndian,
+                    "binary_big_endian" => Encoding::BinaryBigEndian,
+                    _ => return Err(invalid_data(format!("unsupported PLY format {format}"))),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| invalid_data(format!("invalid PLY element count {count}")))?,
+                properties: Vec::new(),
+            }),
+            ["property", rest @ ..] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| invalid_data("PLY property before element".to_string()))?;
+                let property = match rest {
+                    ["list", count, scalar, name] => Property {
+                        name: name.to_string(),
+                        scalar: Scalar::parse(scalar)?,
+                        count: Some(Scalar::parse(count)?),
+                    },
+                    [scalar, name] => Property {
+                        name: name.to_string(),
+                        scalar: Scalar::parse(scalar)?,
+                        count: None,
+                    },
+                    _ => return Err(invalid_data(format!("invalid PLY property {line}"))),
+                };
+                element.properties.push(property);
+            }
+            ["end_header"] => break,
+            _ => {} // comment、obj_info 等
+        }
+    }
+
+    let encoding = encoding.ok_or_else(|| invalid_data("missing PLY format".to_string()))?;
+    Ok((encoding, elements))
+}
+
+/// 读取一行文本（不含换行符）
+fn read_line(data: &[u8], pos: &mut usize) -> io::Result<String> {
+    let start = *pos;
+    while *pos < data.len() && data[*pos] != b'\n' {
+        *pos += 1;
+    }
+    if *pos >= data.len() {
+        return Err(invalid_data("unexpected end of PLY header".to_string()));
+    }
+    let line = String::from_utf8_lossy(&data[start..*pos])
+        .trim()
+        .to_string();
+    *pos += 1;
+    Ok(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\n\
+                          property float y\nproperty float z\n";
+
+    /// 把 text 写入临时 PLY 文件后读取
+    fn load(name: &str, text: &str) -> io::Result<Mesh> {
+        let path =
+            std::env::temp_dir().join(format!("ray-tracing-{}-{name}.ply", std::process::id()));
+        fs::write(&path, text).unwrap();
+        let mesh = Mesh::load_ply(&path);
+        fs::remove_file(&path).unwrap();
+        mesh
+    }
+
+    fn with_face(face: &str) -> String {
+        format!(
+            "{HEADER}element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+             0 0 0\n1 0 0\n0 1 0\n{face}\n"
+        )
+    }
+
+    #[test]
+    fn loads_ascii_triangle() {
+        let mesh = load("triangle", &with_face("3 0 1 2")).unwrap();
+        assert_eq!(mesh.positions.len(), 3);
+        assert_eq!(mesh.indices, [[0, 1, 2]]);
+    }
+
+    #[test]
+    fn rejects_negative_and_out_of_range_indices() {
+        let err = load("negative", &with_face("3 0 -1 2")).err().unwrap();
+        assert!(err.to_string().contains("invalid PLY index -1"), "{err}");
+        assert!(load("range", &with_face("3 0 1 7")).is_err());
+    }
+
+    #[test]
+    fn rejects_point_clouds() {
+        let text = format!("{HEADER}end_header\n0 0 0\n1 0 0\n0 1 0\n");
+        let err = load("points", &text).err().unwrap();
+        assert!(err.to_string().contains("point clouds"), "{err}");
+    }
+}
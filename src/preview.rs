@@ -0,0 +1,500 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    camera::{AutoFocus, Camera},
+    hittable::Hittable,
+    image::Image,
+    math::{Mat3, Onb},
+    progress::CancelToken,
+    scene::Scene,
+    vec3::{Color, Point3, Real, Vec3},
+};
+
+/// 每一遍渲染的每像素采样数
+const PASS_SAMPLES: usize = 1;
+
+/// 请求体的最大长度（字节）
+const MAX_BODY: usize = 64 * 1024;
+
+/// 浏览器中的预览页面：定时拉取帧，拖动环绕、Shift+拖动平移、滚轮推拉、+/- 缩放、F 对焦画面中心
+const VIEWER_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>ray-tracing preview</title>
+<style>
+body { margin: 0; background: #222; color: #ccc; font: 14px sans-serif; }
+img { display: block; max-width: 100%; cursor: move; }
+p { margin: 8px; }
+</style>
+</head>
+<body>
+<img id="frame" draggable="false">
+<p id="status">waiting for the first pass...</p>
+<p>drag: orbit &middot; shift+drag: pan &middot; wheel: dolly &middot; +/-: zoom &middot; f: focus on center</p>
+<script>
+const token = encodeURIComponent(new URLSearchParams(location.search).get("token"));
+const img = document.getElementById("frame");
+const status = document.getElementById("status");
+let busy = false;
+async function refresh() {
+  if (busy) return;
+  busy = true;
+  try {
+    const r = await fetch("/frame.bmp?token=" + token + "&t=" + Date.now());
+    if (r.ok) {
+      const old = img.src;
+      img.src = URL.createObjectURL(await r.blob());
+      if (old) URL.revokeObjectURL(old);
+      status.textContent = r.headers.get("X-Samples") + " samples/pixel";
+    }
+  } finally {
+    busy = false;
+  }
+}
+setInterval(refresh, 250);
+function send(command) {
+  fetch("/command?token=" + token, { method: "POST", body: command });
+}
+let drag = null;
+img.addEventListener("mousedown", e => { drag = { x: e.clientX, y: e.clientY }; });
+addEventListener("mouseup", e => {
+  if (!drag) return;
+  const dx = e.clientX - drag.x, dy = e.clientY - drag.y;
+  drag = null;
+  if (dx == 0 && dy == 0) return;
+  if (e.shiftKey) send("pan " + (-dx / img.width) + " " + (dy / img.height));
+  else send("orbit " + (-dx * 0.5) + " " + (-dy * 0.5));
+});
+img.addEventListener("wheel", e => {
+  e.preventDefault();
+  send("dolly " + (e.deltaY > 0 ? 1.1 : 1 / 1.1));
+});
+addEventListener("keydown", e => {
+  if (e.key == "+" || e.key == "=") send("zoom 0.9");
+  if (e.key == "-") send("zoom 1.1");
+  if (e.key == "f") send("autofocus");
+});
+</script>
+</body>
+</html>
+"#;
+
+/// 预览中的相机操作，文本形式为一行以空白分隔的命令名与参数
+#[derive(Clone, Debug, PartialEq)]
+pub enum CameraCommand {
+    Orbit { yaw: Real, pitch: Real }, // orbit <yaw> <pitch>：绕观察目标旋转（度）
+    Pan { dx: Real, dy: Real },       // pan <dx> <dy>：沿画面平移，以到观察目标的距离为单位
+    Dolly(Real),                      // dolly <k>：到观察目标的距离乘以 k
+    Zoom(Real),                       // zoom <k>：视角乘以 k
+    LookFrom(Point3),                 // lookfrom <x> <y> <z>
+    LookAt(Point3),                   // lookat <x> <y> <z>
+    Vfov(Real),                       // vfov <度>
+    FocusDist(Real),                  // focus <距离>：手动对焦并关闭自动对焦
+    FNumber(Real),                    // fnumber <f 值>
+    AutoFocus,                        // autofocus：开启对画面中心的自动对焦
+    Quit,                             // quit：结束预览
+}
+
+impl FromStr for CameraCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut words = s.split_whitespace();
+        let name = words.next().ok_or("empty command")?;
+        let args = words
+            .map(|w| {
+                w.parse::<Real>()
+                    .map_err(|_| format!("invalid number {w:?}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let arity = |n: usize| {
+            if args.len() == n {
+                Ok(())
+            } else {
+                Err(format!("{name} takes {n} argument(s)"))
+            }
+        };
+        let command = match name {
+            "orbit" => arity(2).map(|_| Self::Orbit {
+                yaw: args[0],
+                pitch: args[1],
+            }),
+            "pan" => arity(2).map(|_| Self::Pan {
+                dx: args[0],
+                dy: args[1],
+            }),
+            "dolly" => arity(1).map(|_| Self::Dolly(args[0])),
+            "zoom" => arity(1).map(|_| Self::Zoom(args[0])),
+            "lookfrom" => arity(3).map(|_| Self::LookFrom(Point3::new(args[0], args[1], args[2]))),
+            "lookat" => arity(3).map(|_| Self::LookAt(Point3::new(args[0], args[1], args[2]))),
+            "vfov" => arity(1).map(|_| Self::Vfov(args[0])),
+            "focus" => arity(1).map(|_| Self::FocusDist(args[0])),
+            "fnumber" => arity(1).map(|_| Self::FNumber(args[0])),
+            "autofocus" => arity(0).map(|_| Self::AutoFocus),
+            "quit" => arity(0).map(|_| Self::Quit),
+            _ => Err(format!("unknown command {name:?}")),
+        }?;
+        if args.iter().any(|a| !a.is_finite()) {
+            return Err(format!("{name}: arguments must be finite"));
+        }
+        Ok(command)
+    }
+}
+
+impl CameraCommand {
+    /// 修改相机参数，Quit 不做任何修改；lookfrom 与 lookat 重合，
+    /// 或观察方向与 vup 平行时返回错误且不修改相机
+    pub fn apply(&self, camera: &mut Camera) -> Result<(), String> {
+        let offset = camera.lookfrom - camera.lookat;
+        let basis = Onb::with_up(&offset, &camera.vup);
+        match *self {
+            Self::Orbit { yaw, pitch } => {
+                let rotation = Mat3::rotation(&basis.u, pitch.to_radians())
+                    * Mat3::rotation(&camera.vup, yaw.to_radians());
+                let rotated = rotation * offset;
+                // 不越过正上方或正下方，以免画面翻转
+                if rotated
+                    .unit_vector()
+                    .cross(&camera.vup.unit_vector())
+                    .length()
+                    > 1e-3
+                {
+                    camera.lookfrom = camera.lookat + rotated;
+                }
+            }
+            Self::Pan { dx, dy } => {
+                let shift = (dx * basis.u + dy * basis.v) * offset.length();
+                camera.lookfrom += shift;
+                camera.lookat += shift;
+            }
+            Self::Dolly(k) => {
+                camera.lookfrom = camera.lookat + offset * k.max(1e-3);
+                camera.focus_dist *= k.max(1e-3);
+            }
+            Self::Zoom(k) => camera.vfov = (camera.vfov * k).clamp(1.0, 179.0),
+            Self::LookFrom(p) => {
+                check_view(&p, &camera.lookat, &camera.vup)?;
+                camera.lookfrom = p;
+            }
+            Self::LookAt(p) => {
+                check_view(&camera.lookfrom, &p, &camera.vup)?;
+                camera.lookat = p;
+            }
+            Self::Vfov(vfov) => camera.vfov = vfov.clamp(1.0, 179.0),
+            Self::FocusDist(d) => {
+                camera.focus_dist = d.max(1e-3);
+                camera.autofocus = None;
+            }
+            Self::FNumber(n) => camera.f_number = n.max(1e-3),
+            Self::AutoFocus => camera.autofocus = Some(AutoFocus::Center),
+            Self::Quit => {}
+        }
+        Ok(())
+    }
+}
+
+/// 检查视线能否确定相机朝向：lookfrom 与 lookat 不能重合，视线不能与 vup 平行
+fn check_view(lookfrom: &Point3, lookat: &Point3, vup: &Vec3) -> Result<(), String> {
+    let offset = lookfrom - lookat;
+    if offset.near_zero() {
+        return Err("lookfrom and lookat coincide".to_string());
+    }
+    if offset.unit_vector().cross(&vup.unit_vector()).length() <= 1e-3 {
+        return Err("view direction is parallel to vup".to_string());
+    }
+    Ok(())
+}
+
+/// 最近一次发布的帧
+#[derive(Default)]
+struct Frame {
+    bmp: Vec<u8>,   // BMP 编码的图像
+    samples: usize, // 每像素累计采样数
+}
+
+/// 渐进式交互预览
+///
+/// 在 addr 上提供 HTTP 服务，直到收到 quit 命令。每次预览生成随机的访问令牌，
+/// 各请求须在查询参数 `token` 中带上该令牌，带有 `Origin` 头的请求还须来自同一来源，
+/// 以免其他网页操纵相机；带令牌的页面地址打印到标准错误：
+/// - `GET /`：浏览器预览页面
+/// - `GET /frame.bmp`：当前累计的图像（BMP），响应头 `X-Samples` 为每像素采样数；
+///   第一遍完成前返回 503
+/// - `POST /command`：请求体每行一条 [`CameraCommand`]
+///
+/// 每一遍每像素采样 1 次并与之前的结果平均，累计到相机的 samples_per_pixel 后
+/// 停止并等待命令；相机改变时放弃当前一遍并从头累计。设置了自动对焦时，
+/// 每次相机改变后按 scene 与 world 重新对焦
+pub fn serve(
+    camera: &mut Camera,
+    scene: &Scene,
+    world: &dyn Hittable,
+    addr: impl ToSocketAddrs,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    let token = format!("{:032x}", rand::random::<u128>());
+    eprintln!(
+        "Preview at http://{}/?token={token}",
+        listener.local_addr()?
+    );
+
+    let frame = Arc::new(Mutex::new(Frame::default()));
+    let stop = Arc::new(AtomicBool::new(false));
+    let (commands, receiver) = mpsc::channel();
+    let server = {
+        let frame = Arc::clone(&frame);
+        let stop = Arc::clone(&stop);
+        thread::spawn(move || accept_loop(listener, &token, &frame, &commands, &stop))
+    };
+
+    let (target, seed) = (camera.samples_per_pixel, camera.seed);
+    camera.samples_per_pixel = PASS_SAMPLES;
+    let result = render_loop(camera, scene, world, target, &receiver, &frame);
+    camera.samples_per_pixel = target;
+    camera.seed = seed;
+
+    stop.store(true, Ordering::Relaxed);
+    server.join().unwrap();
+    result
+}
+
+/// 逐遍渲染并发布帧，直到收到 quit 或服务线程退出
+fn render_loop(
+    camera: &mut Camera,
+    scene: &Scene,
+    world: &dyn Hittable,
+    target: usize,
+    receiver: &Receiver<CameraCommand>,
+    frame: &Mutex<Frame>,
+) -> io::Result<()> {
+    let seed = camera.seed;
+    let mut sum: Vec<Color> = Vec::new(); // 各遍结果之和
+    let mut passes = 0;
+    let mut pending: Vec<CameraCommand> = Vec::new();
+    camera.auto_focus(scene, world)?;
+    loop {
+        if !pending.is_empty() {
+            for command in pending.drain(..) {
+                if command == CameraCommand::Quit {
+                    return Ok(());
+                }
+                if let Err(e) = command.apply(camera) {
+                    eprintln!("\rPreview command {command:?} ignored: {e}");
+                }
+            }
+            camera.auto_focus(scene, world)?;
+            passes = 0;
+        }
+        if passes * PASS_SAMPLES >= target {
+            // 已收敛，等待下一条命令
+            match receiver.recv() {
+                Ok(command) => pending.push(command),
+                Err(_) => return Ok(()),
+            }
+            continue;
+        }
+
+        // 每一遍使用不同的种子；收到命令时取消当前一遍
+        camera.seed = seed.wrapping_add(passes as u64);
+        let cancel = CancelToken::new();
+        let pass = camera.render_image(
+            world,
+            &mut |_| {
+                while let Ok(command) = receiver.try_recv() {
+                    pending.push(command);
+                    cancel.cancel();
+                }
+            },
+            &cancel,
+        );
+        if cancel.is_cancelled() {
+            continue;
+        }
+
+        if passes == 0 {
+            sum = pass.pixels.clone();
+        } else {
+            for (s, p) in sum.iter_mut().zip(pass.pixels.iter()) {
+                *s += *p;
+            }
+        }
+        passes += 1;
+        eprint!("\rPreview: {} samples/pixel ", passes * PASS_SAMPLES);
+
+        let image = Image {
+            pixels: sum.iter().map(|s| s / passes as Real).collect(),
+            ..pass
+        };
+        let mut bmp = Vec::new();
+        image.write_bmp(&mut bmp)?;
+        *frame.lock().unwrap() = Frame {
+            bmp,
+            samples: passes * PASS_SAMPLES,
+        };
+    }
+}
+
+/// 接受连接，每个连接在单独的线程中处理，stop 置位后返回
+fn accept_loop(
+    listener: TcpListener,
+    token: &str,
+    frame: &Arc<Mutex<Frame>>,
+    commands: &Sender<CameraCommand>,
+    stop: &AtomicBool,
+) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let token = token.to_string();
+                let frame = Arc::clone(frame);
+                let commands = commands.clone();
+                thread::spawn(move || {
+                    if let Err(e) = handle(stream, &token, &frame, &commands) {
+                        eprintln!("\rPreview connection error: {e}");
+                    }
+                });
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(20));
+            }
+            Err(e) => eprintln!("\rPreview accept error: {e}"),
+        }
+    }
+}
+
+/// 处理一个 HTTP 请求，响应后关闭连接；令牌不符或来源不同的请求返回 403
+fn handle(
+    stream: TcpStream,
+    token: &str,
+    frame: &Mutex<Frame>,
+    commands: &Sender<CameraCommand>,
+) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut content_length = 0;
+    let (mut host, mut origin) = (None, None);
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let (name, value) = (name.trim(), value.trim());
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse().unwrap_or(0);
+            } else if name.eq_ignore_ascii_case("host") {
+                host = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case("origin") {
+                origin = Some(value.to_string());
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        return respond(stream, "413 Payload Too Large", "text/plain", &[], b"");
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let authorized = query
+        .split('&')
+        .any(|pair| pair == format!("token={token}"));
+    let same_origin = match (&origin, &host) {
+        (None, _) => true,
+        (Some(origin), Some(host)) => *origin == format!("http://{host}"),
+        (Some(_), None) => false,
+    };
+    if !authorized || !same_origin {
+        return respond(stream, "403 Forbidden", "text/plain", &[], b"");
+    }
+    match (method, path) {
+        ("GET", "/") => respond(
+            stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            &[],
+            VIEWER_HTML.as_bytes(),
+        ),
+        ("GET", "/frame.bmp") => {
+            let (bmp, samples) = {
+                let frame = frame.lock().unwrap();
+                (frame.bmp.clone(), frame.samples)
+            };
+            if bmp.is_empty() {
+                return respond(stream, "503 Service Unavailable", "text/plain", &[], b"");
+            }
+            let samples = samples.to_string();
+            respond(
+                stream,
+                "200 OK",
+                "image/bmp",
+                &[("X-Samples", &samples), ("Cache-Control", "no-store")],
+                &bmp,
+            )
+        }
+        ("POST", "/command") => {
+            let parsed = String::from_utf8_lossy(&body)
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(str::parse)
+                .collect::<Result<Vec<CameraCommand>, _>>();
+            match parsed {
+                Ok(parsed) => {
+                    for command in parsed {
+                        // 渲染已结束时发送失败，忽略即可
+                        let _ = commands.send(command);
+                    }
+                    respond(stream, "204 No Content", "text/plain", &[], b"")
+                }
+                Err(e) => respond(
+                    stream,
+                    "400 Bad Request",
+                    "text/plain",
+                    &[],
+                    format!("{e}\n").as_bytes(),
+                ),
+            }
+        }
+        _ => respond(stream, "404 Not Found", "text/plain", &[], b""),
+    }
+}
+
+fn respond(
+    mut stream: TcpStream,
+    status: &str,
+    content_type: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        body.len()
+    )?;
+    for (name, value) in headers {
+        write!(stream, "{name}: {value}\r\n")?;
+    }
+    stream.write_all(b"\r\n")?;
+    stream.write_all(body)?;
+    stream.flush()
+}
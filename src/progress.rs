@@ -0,0 +1,99 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::vec3::Real;
+
+/// 图像中的矩形区域（像素），立体渲染时 y 为在上下拼接后的整幅图像中的坐标
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// 渲染进度，每完成一个图块报告一次
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    pub tile: Tile,               // 刚完成的图块
+    pub tiles_done: usize,        // 已完成的图块数
+    pub tiles_total: usize,       // 图块总数
+    pub samples_done: u64,        // 已完成的像素采样数
+    pub elapsed: Duration,        // 已用时间
+    pub samples_per_second: Real, // 平均每秒像素采样数
+    pub eta: Option<Duration>,    // 按平均速度估计的剩余时间，尚无法估计时为 None
+}
+
+impl Progress {
+    /// 由已完成的采样数与用时推算速度和剩余时间
+    pub fn new(
+        tile: Tile,
+        tiles_done: usize,
+        tiles_total: usize,
+        samples_done: u64,
+        samples_total: u64,
+        elapsed: Duration,
+    ) -> Self {
+        let samples_per_second = samples_done as Real / elapsed.as_secs_f64() as Real;
+        let eta = (samples_done > 0).then(|| {
+            elapsed.mul_f64(samples_total.saturating_sub(samples_done) as f64 / samples_done as f64)
+        });
+        Self {
+            tile,
+            tiles_done,
+            tiles_total,
+            samples_done,
+            elapsed,
+            samples_per_second,
+            eta,
+        }
+    }
+
+    /// 完成比例，取值 [0, 1]
+    pub fn fraction(&self) -> Real {
+        if self.tiles_total == 0 {
+            return 1.0;
+        }
+        self.tiles_done as Real / self.tiles_total as Real
+    }
+}
+
+/// 单行进度文本：已完成图块、速度与剩余时间
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let eta = self
+            .eta
+            .map_or("-".to_string(), |eta| format!("{:.0}s", eta.as_secs_f64()));
+        write!(
+            f,
+            "Tiles: {}/{}, {:.0} samples/s, ETA {}",
+            self.tiles_done, self.tiles_total, self.samples_per_second, eta
+        )
+    }
+}
+
+/// 取消令牌，可复制到其他线程；取消后渲染在当前图块完成时停止并返回已完成的部分
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    lens::{Aperture, LensDistortion},
+    math::{Mat3, Onb},
+    ray::{Ray, RayKind},
+    vec3::{consts::PI, Point3, Real, Vec3},
+};
+
+/// 视图参数，由相机初始化时计算并传给投影
+#[derive(Default)]
+pub struct View {
+    pub center: Point3,    // 相机中心
+    pub basis: Onb,        // 相机坐标系：u 向右，v 向上，w 指向相机后方
+    pub aspect: Real,      // 图像宽高比
+    pub vfov: Real,        // 垂直视角
+    pub focus_dist: Real,  // 焦距
+    pub lens_radius: Real, // 透镜半径
+    pub eye_offset: Real,  // 立体渲染时沿 u 方向的眼睛偏移，左眼为负
+}
+
+impl View {
+    /// 眼睛位置（相机中心加上立体偏移）
+    pub fn eye(&self) -> Point3 {
+        self.center + self.basis.u * self.eye_offset
+    }
+
+    /// 将相机坐标系 (x 向右, y 向上, z 向后) 下的向量转换到世界坐标系
+    pub fn to_world(&self, x: Real, y: Real, z: Real) -> Vec3 {
+        self.basis.local(&Vec3::new(x, y, z))
+    }
+}
+
+/// 相机投影模型
+#[typetag::serde(tag = "type")]
+pub trait Projection: Sync {
+    /// 由胶片坐标 (s, t) 生成光线，s 向右、t 向下，取值 [0, 1]；
+    /// 该位置不在成像范围内时返回 None
+    fn generate_ray(&self, view: &View, s: Real, t: Real) -> Option<Ray>;
+
+    /// 景深是否遵循焦平面与画面平行的薄透镜模型，即弥散圆只取决于深度；默认为 false
+    fn defocus_by_depth(&self) -> bool {
+        false
+    }
+}
+
+/// 透视投影（薄透镜），由 vfov 决定视角，透镜半径决定景深
+#[derive(Serialize, Deserialize)]
+pub struct ThinLens {
+    pub aperture: Aperture,                 // 光圈形状
+    pub cat_eye: Real,                      // 猫眼渐晕强度，0 表示关闭
+    pub tilt_x: Real,                       // 焦平面绕水平轴的倾斜角（度）
+    pub tilt_y: Real,                       // 焦平面绕垂直轴的倾斜角（度）
+    pub shift_x: Real,                      // 水平移轴量（以画面高度为单位）
+    pub shift_y: Real,                      // 垂直移轴量（以画面高度为单位）
+    pub distortion: Option<LensDistortion>, // 镜头畸变
+}
+
+impl Default for ThinLens {
+    fn default() -> Self {
+        Self {
+            aperture: Aperture::Circular,
+            cat_eye: 0.0,
+            tilt_x: 0.0,
+            tilt_y: 0.0,
+            shift_x: 0.0,
+            shift_y: 0.0,
+            distortion: None,
+        }
+    }
+}
+
+#[typetag::serde]
+impl Projection for ThinLens {
+    fn generate_ray(&self, view: &View, s: Real, t: Real) -> Option<Ray> {
+        let half_height = (view.vfov.to_radians() / 2.0).tan(); // 单位距离处的半高
+        let half_width = half_height * view.aspect; // 单位距离处的半宽
+
+        // 胶片上的归一化相机坐标
+        let film_x = 2.0 * s - 1.0;
+        let film_y = 1.0 - 2.0 * t;
+        let mut x = film_x * half_width + self.shift_x * 2.0 * half_height;
+        let mut y = film_y * half_height + self.shift_y * 2.0 * half_height;
+        if let Some(distortion) = &self.distortion {
+            (x, y) = distortion.undistort(x, y);
+        }
+
+        // 针孔方向与焦平面的交点即对焦点
+        let n = self.focal_plane_normal();
+        let denom = x * n.x + y * n.y - n.z;
+        if denom.abs() < 1e-12 {
+            return None;
+        }
+        let k = -view.focus_dist * n.z / denom;
+        if k <= 0.0 {
+            return None;
+        }
+
+        let eye = view.eye();
+        let target = eye + view.to_world(x * k, y * k, -k);
+
+        let origin = if view.lens_radius <= 0.0 {
+            eye
+        } else {
+            let (lx, ly) = self.aperture.sample();
+            if self.cat_eye > 0.0 {
+                // 镜筒的出瞳随视场偏移，落在其外的光线被遮挡
+                let cx = self.cat_eye * film_x * view.aspect;
+                let cy = self.cat_eye * film_y;
+                if (lx - cx).powi(2) + (ly - cy).powi(2) > 1.0 {
+                    return None;
+                }
+            }
+            eye + view.to_world(lx * view.lens_radius, ly * view.lens_radius, 0.0)
+        };
+        let direction = target - origin;
+
+        Some(Ray::new(origin, direction, RayKind::Camera))
+    }
+
+    fn defocus_by_depth(&self) -> bool {
+        self.tilt_x == 0.0 && self.tilt_y == 0.0
+    }
+}
+
+impl ThinLens {
+    /// 相机坐标系下焦平面的法线，无倾斜时为 (0, 0, 1)
+    fn focal_plane_normal(&self) -> Vec3 {
+        let tilt =
+            Mat3::rotation_y(self.tilt_y.to_radians()) * Mat3::rotation_x(self.tilt_x.to_radians());
+        tilt * Vec3::new(0.0, 0.0, 1.0)
+    }
+}
+
+/// 正交投影，所有光线沿观察方向平行射出
+#[derive(Serialize, Deserialize)]
+pub struct Orthographic {
+    pub height: Real, // 视口高度（世界单位）
+}
+
+impl Default for Orthographic {
+    fn default() -> Self {
+        Self { height: 2.0 }
+    }
+}
+
+#[typetag::serde]
+impl Projection for Orthographic {
+    fn generate_ray(&self, view: &View, s: Real, t: Real) -> Option<Ray> {
+        let half_height = self.height / 2.0;
+        let half_width = half_height * view.aspect;
+
+        let origin = view.eye()
+            + view.to_world(
+                (2.0 * s - 1.0) * half_width,
+                (1.0 - 2.0 * t) * half_height,
+                0.0,
+            );
+
+        Some(Ray::new(origin, -view.basis.w, RayKind::Camera))
+    }
+}
+
+/// 等距鱼眼投影，成像圆内切于图像高度，像距与入射角成正比
+#[derive(Serialize, Deserialize)]
+pub struct Fisheye {
+    pub fov: Real, // 成像圆对应的视角（角度）
+}
+
+impl Default for Fisheye {
+    fn default() -> Self {
+        Self { fov: 180.0 }
+    }
+}
+
+#[typetag::serde]
+impl Projection for Fisheye {
+    fn generate_ray(&self, view: &View, s: Real, t: Real) -> Option<Ray> {
+        let x = (2.0 * s - 1.0) * view.aspect;
+        let y = 1.0 - 2.0 * t;
+        let r = (x * x + y * y).sqrt(); // 到成像圆中心的归一化距离
+        if r > 1.0 {
+            return None;
+        }
+
+        let theta = r * self.fov.to_radians() / 2.0; // 与光轴的夹角
+        let phi = y.atan2(x);
+        let direction = view.to_world(
+            theta.sin() * phi.cos(),
+            theta.sin() * phi.sin(),
+            -theta.cos(),
+        );
+
+        Some(Ray::new(view.eye(), direction, RayKind::Camera))
+    }
+}
+
+/// 等距柱状（360° 全景）投影，图像宽高比应为 2:1
+///
+/// 立体渲染时使用全向立体（ODS）方式，眼睛偏移随水平方向旋转
+#[derive(Default, Serialize, Deserialize)]
+pub struct Equirectangular;
+
+#[typetag::serde]
+impl Projection for Equirectangular {
+    fn generate_ray(&self, view: &View, s: Real, t: Real) -> Option<Ray> {
+        let lon = (2.0 * s - 1.0) * PI; // 经度，图像中心对应观察方向
+        let lat = (0.5 - t) * PI; // 纬度
+
+        let direction = view.to_world(lat.cos() * lon.sin(), lat.sin(), -lat.cos() * lon.cos());
+        let origin = view.center + view.to_world(lon.cos(), 0.0, lon.sin()) * view.eye_offset;
+
+        Some(Ray::new(origin, direction, RayKind::Camera))
+    }
+}
+
+/// 立体输出方式
+#[derive(Serialize, Deserialize)]
+pub enum StereoOutput {
+    Left,      // 仅左眼
+    Right,     // 仅右眼
+    OverUnder, // 上下排列，左眼在上
+}
+
+/// 立体渲染参数
+#[derive(Serialize, Deserialize)]
+pub struct Stereo {
+    pub eye_separation: Real, // 瞳距（世界单位）
+    pub output: StereoOutput,
+}
+
+impl Stereo {
+    /// 各输出眼睛沿 u 方向的偏移，按图像中自上而下的顺序排列
+    pub fn eye_offsets(&self) -> Vec<Real> {
+        let half = self.eye_separation / 2.0;
+        match self.output {
+            StereoOutput::Left => vec![-half],
+            StereoOutput::Right => vec![half],
+            StereoOutput::OverUnder => vec![-half, half],
+        }
+    }
+}
@@ -1,19 +1,34 @@
-use crate::vec3::Vec3;
+use crate::vec3::{Point3, Real, Vec3};
+
+/// 光线类型，用于按类型控制物体的可见性
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RayKind {
+    #[default]
+    Camera, // 相机发出的主光线
+    Reflection, // 反射（含漫反射）光线
+    Refraction, // 折射光线
+    Shadow,     // 光源采样的阴影光线
+}
 
 #[derive(Default)]
 pub struct Ray {
-    pub origin: Vec3,
+    pub origin: Point3,
     pub direction: Vec3,
+    pub kind: RayKind,
 }
 
 impl Ray {
     /// 光线上的点
-    pub fn at(&self, t: f64) -> Vec3 {
-        &self.origin + &self.direction * t
+    pub fn at(&self, t: Real) -> Point3 {
+        self.origin + self.direction * t
     }
 
     /// 创建光线
-    pub fn new(origin: Vec3, direction: Vec3) -> Ray {
-        Ray { origin, direction }
+    pub fn new(origin: Point3, direction: Vec3, kind: RayKind) -> Ray {
+        Ray {
+            origin,
+            direction,
+            kind,
+        }
     }
 }
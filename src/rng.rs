@@ -0,0 +1,42 @@
+use std::cell::RefCell;
+
+use rand::{
+    distributions::{uniform::SampleRange, uniform::SampleUniform, Distribution, Standard},
+    rngs::SmallRng,
+    Rng, SeedableRng,
+};
+
+thread_local! {
+    /// 当前线程的随机数发生器，渲染每个光线包前由 seed 重新设定
+    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_entropy());
+}
+
+/// 以 seed 重新设定当前线程的随机数发生器，之后的随机数序列完全由 seed 决定
+pub fn seed(seed: u64) {
+    RNG.with_borrow_mut(|rng| *rng = SmallRng::seed_from_u64(seed));
+}
+
+/// 取一个随机值，浮点数在 [0, 1) 中均匀分布
+#[inline]
+pub fn random<T>() -> T
+where
+    Standard: Distribution<T>,
+{
+    RNG.with_borrow_mut(|rng| rng.gen())
+}
+
+/// 在 range 中均匀取值
+#[inline]
+pub fn gen_range<T: SampleUniform, R: SampleRange<T>>(range: R) -> T {
+    RNG.with_borrow_mut(|rng| rng.gen_range(range))
+}
+
+/// 把若干个值混合为一个种子（SplitMix64 终混函数）
+pub fn mix_seed(values: &[u64]) -> u64 {
+    values.iter().fold(0x9e37_79b9_7f4a_7c15, |h, &v| {
+        let mut z = (h ^ v).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    })
+}
@@ -0,0 +1,562 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+    rc::Rc,
+    sync::Arc,
+};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::{
+    aabb::Aabb,
+    animation::Animation,
+    bvh::Bvh,
+    camera::Camera,
+    hittable::{Hittable, HittableList, SurfaceInteraction},
+    image::invalid_data,
+    instance::Instance,
+    interval::Interval,
+    library::{self, Library},
+    light::LightSet,
+    material::Material,
+    math::Transform,
+    packet::{PacketRecords, RayPacket, PACKET_WIDTH},
+    ray::{Ray, RayKind},
+    simd::Mask,
+    stats::PhaseTimer,
+    texture::Texture,
+};
+
+/// 光源链接中代表环境光的名称
+pub const ENVIRONMENT_LIGHT: &str = "environment";
+
+/// 场景文件格式的版本，格式发生不兼容的变化时递增
+pub const SCENE_FORMAT_VERSION: u32 = 2;
+
+/// 节点对各类光线是否可见，子节点只在父节点可见时可见
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Visibility {
+    pub camera: bool,     // 对相机主光线可见
+    pub reflection: bool, // 在反射中可见
+    pub refraction: bool, // 在折射中可见
+    pub shadow: bool,     // 投射阴影
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Self {
+            camera: true,
+            reflection: true,
+            refraction: true,
+            shadow: true,
+        }
+    }
+}
+
+impl Visibility {
+    /// 父节点可见性为 self 时子节点的实际可见性
+    fn inherit(&self, child: &Visibility) -> Self {
+        Self {
+            camera: self.camera && child.camera,
+            reflection: self.reflection && child.reflection,
+            refraction: self.refraction && child.refraction,
+            shadow: self.shadow && child.shadow,
+        }
+    }
+
+    /// 对类型为 kind 的光线是否可见
+    pub fn visible_to(&self, kind: RayKind) -> bool {
+        match kind {
+            RayKind::Camera => self.camera,
+            RayKind::Reflection => self.reflection,
+            RayKind::Refraction => self.refraction,
+            RayKind::Shadow => self.shadow,
+        }
+    }
+}
+
+/// 展开场景图时从父节点继承的属性
+struct Inherited {
+    transform: Transform,   // 世界空间的变换
+    visibility: Visibility, // 可见性
+    links: LightSet,        // 照亮该子树的光源
+    light: LightSet,        // 子树发光时所属的光源
+}
+
+impl Inherited {
+    /// 根节点继承的属性
+    fn root() -> Self {
+        Self {
+            transform: Transform::default(),
+            visibility: Visibility::default(),
+            links: LightSet::ALL,
+            light: LightSet::UNLINKED,
+        }
+    }
+}
+
+/// 场景图节点：可以挂一个物体，也可以作为分组包含子节点
+#[derive(Serialize, Deserialize)]
+pub struct Node {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub transform: Transform, // 相对父节点的变换
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub visibility: Visibility, // 可见性
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub light_links: Option<Vec<String>>, // 照亮该子树的光源节点名称，None 时沿用父节点
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object: Option<Rc<dyn Hittable>>, // 节点上的物体，分组节点为 None
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Node>, // 子节点
+}
+
+impl Node {
+    /// 空的分组节点
+    pub fn group(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            transform: Transform::default(),
+            visibility: Visibility::default(),
+            light_links: None,
+            object: None,
+            children: Vec::new(),
+        }
+    }
+
+    /// 挂有物体的节点
+    pub fn object(name: &str, object: Rc<dyn Hittable>) -> Self {
+        Self {
+            object: Some(object),
+            ..Self::group(name)
+        }
+    }
+
+    pub fn with_transform(self, transform: Transform) -> Self {
+        Self { transform, ..self }
+    }
+
+    pub fn with_visibility(self, visibility: Visibility) -> Self {
+        Self { visibility, ..self }
+    }
+
+    /// 只让名为 lights 的光源节点（或 ENVIRONMENT_LIGHT）照亮该子树
+    pub fn with_light_links(self, lights: &[&str]) -> Self {
+        Self {
+            light_links: Some(lights.iter().map(|name| name.to_string()).collect()),
+            ..self
+        }
+    }
+
+    /// 添加子节点，返回新添加的子节点
+    pub fn add(&mut self, child: Node) -> &mut Node {
+        self.children.push(child);
+        self.children.last_mut().unwrap()
+    }
+
+    /// 深度优先查找名为 name 的节点（包括自身）
+    pub fn find(&self, name: &str) -> Option<&Node> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(name))
+    }
+
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Node> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.children
+            .iter_mut()
+            .find_map(|child| child.find_mut(name))
+    }
+
+    /// 查找名为 name 的节点及其父节点在世界空间的变换，parent 为该节点的父节点的变换
+    fn find_with_transform(&self, name: &str, parent: Transform) -> Option<(&Node, Transform)> {
+        if self.name == name {
+            return Some((self, parent));
+        }
+        let transform = parent * self.transform;
+        self.children
+            .iter()
+            .find_map(|child| child.find_with_transform(name, transform))
+    }
+
+    /// 统计子树中各名称的节点数
+    fn count_names<'a>(&'a self, counts: &mut HashMap<&'a str, usize>) {
+        *counts.entry(self.name.as_str()).or_default() += 1;
+        for child in self.children.iter() {
+            child.count_names(counts);
+        }
+    }
+
+    /// 子树中被光源链接引用的节点名称
+    fn linked_lights<'a>(&'a self, names: &mut Vec<&'a str>) {
+        for name in self.light_links.iter().flatten() {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+        for child in self.children.iter() {
+            child.linked_lights(names);
+        }
+    }
+
+    /// 把以该节点为根的子树展开到 list，lights 为被链接光源的编号；
+    /// 带变换的节点的物体中已包含实例时返回错误
+    fn flatten(
+        &self,
+        parent: &Inherited,
+        lights: &HashMap<&str, usize>,
+        list: &mut HittableList,
+    ) -> io::Result<()> {
+        let light = |name: &str| {
+            lights
+                .get(name)
+                .and_then(|&id| LightSet::single(id))
+                .ok_or_else(|| invalid_data(format!("linked light {name:?} has no valid id")))
+        };
+        let links = match &self.light_links {
+            Some(names) => names.iter().try_fold(LightSet::NONE, |links, name| {
+                Ok::<_, io::Error>(links.union(&light(name)?))
+            })?,
+            None => parent.links,
+        };
+        let inherited = Inherited {
+            transform: parent.transform * self.transform,
+            visibility: parent.visibility.inherit(&self.visibility),
+            links,
+            light: if lights.contains_key(self.name.as_str()) {
+                light(&self.name)?
+            } else {
+                parent.light
+            },
+        };
+
+        if let Some(object) = &self.object {
+            let mut object = object.clone();
+            if inherited.transform != Transform::default() {
+                object = Rc::new(
+                    Instance::new(object, inherited.transform)
+                        .map_err(|e| invalid_data(format!("node {:?}: {e}", self.name)))?,
+                );
+            }
+            if inherited.visibility != Visibility::default()
+                || inherited.links != LightSet::ALL
+                || inherited.light != LightSet::UNLINKED
+            {
+                object = Rc::new(Tagged {
+                    object,
+                    visibility: inherited.visibility,
+                    links: inherited.links,
+                    light: inherited.light,
+                });
+            }
+            list.add(object);
+        }
+        for child in self.children.iter() {
+            child.flatten(&inherited, lights, list)?;
+        }
+        Ok(())
+    }
+}
+
+/// 序列化时省略取默认值的字段
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// 场景：节点层级以及按名称管理的材质与纹理，渲染前编译为 BVH
+///
+/// 序列化时资源库先于节点写出，库中的材质与纹理在被引用处只写出名称，
+/// 读取后仍共享同一份资源；不在库中的资源内嵌在引用处
+#[derive(Serialize)]
+#[serde(remote = "Self")]
+pub struct Scene {
+    pub textures: Library<Arc<dyn Texture>>,
+    pub materials: Library<Rc<dyn Material>>,
+    pub root: Node,
+}
+
+/// 读取场景时先缓存资源库中的各资源，以便按名称的引用不受字段顺序影响
+#[derive(Deserialize)]
+struct SceneData {
+    #[serde(default)]
+    textures: BTreeMap<String, Value>,
+    #[serde(default)]
+    materials: BTreeMap<String, Value>,
+    root: Value,
+}
+
+impl Serialize for Scene {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        library::scope(|| {
+            self.textures.register();
+            self.materials.register();
+            Scene::serialize(self, serializer)
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Scene {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = SceneData::deserialize(deserializer)?;
+        library::scope(|| {
+            Ok(Self {
+                textures: Library::load(data.textures).map_err(de::Error::custom)?,
+                materials: Library::load(data.materials).map_err(de::Error::custom)?,
+                root: serde_json::from_value(data.root).map_err(de::Error::custom)?,
+            })
+        })
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self {
+            root: Node::group("root"),
+            materials: Library::default(),
+            textures: Library::default(),
+        }
+    }
+}
+
+impl Scene {
+    /// 在根节点下添加节点，返回新添加的节点
+    pub fn add(&mut self, node: Node) -> &mut Node {
+        self.root.add(node)
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Node> {
+        self.root.find(name)
+    }
+
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Node> {
+        self.root.find_mut(name)
+    }
+
+    /// 展开层级，合并各级变换、可见性与光源链接，构建加速结构
+    ///
+    /// 以下情况返回错误：被链接的光源（不含环境光）多于 LightSet::MAX_LIGHT_ID 个、
+    /// 被链接的光源名称没有对应节点或对应多个节点、有节点使用保留名称 ENVIRONMENT_LIGHT，
+    /// 或带变换的节点的物体中已包含实例
+    pub fn build(&self) -> io::Result<Bvh> {
+        let _timer = PhaseTimer::start("build");
+        let mut list = HittableList::new();
+        self.root
+            .flatten(&Inherited::root(), &self.light_ids()?, &mut list)?;
+        Ok(Bvh::new(list))
+    }
+
+    /// 名为 name 的节点子树在世界空间中的几何体，用于对焦等查询；找不到节点或子树中没有物体时返回 None
+    pub fn build_node(&self, name: &str) -> io::Result<Option<Bvh>> {
+        let Some((node, transform)) = self.root.find_with_transform(name, Transform::default())
+        else {
+            return Ok(None);
+        };
+        let parent = Inherited {
+            transform,
+            ..Inherited::root()
+        };
+        let mut list = HittableList::new();
+        node.flatten(&parent, &self.light_ids()?, &mut list)?;
+        Ok((!list.objects.is_empty()).then(|| Bvh::new(list)))
+    }
+
+    /// 被链接的光源及其编号，环境光为 0；光源按节点名称编号，因此名称必须存在且唯一
+    fn light_ids(&self) -> io::Result<HashMap<&str, usize>> {
+        let mut counts = HashMap::new();
+        self.root.count_names(&mut counts);
+        if counts.contains_key(ENVIRONMENT_LIGHT) {
+            return Err(invalid_data(format!(
+                "node name {ENVIRONMENT_LIGHT:?} is reserved for the environment light"
+            )));
+        }
+
+        let mut names = vec![ENVIRONMENT_LIGHT];
+        self.root.linked_lights(&mut names);
+        if names.len() > LightSet::MAX_LIGHT_ID + 1 {
+            return Err(invalid_data(format!(
+                "{} lights are linked, at most {} are supported",
+                names.len() - 1,
+                LightSet::MAX_LIGHT_ID
+            )));
+        }
+        for name in &names[1..] {
+            match counts.get(name) {
+                None => return Err(invalid_data(format!("linked light {name:?} not found"))),
+                Some(&n @ 2..) => {
+                    return Err(invalid_data(format!(
+                        "linked light {name:?} is ambiguous: {n} nodes have that name"
+                    )))
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(names.into_iter().zip(0..).collect())
+    }
+}
+
+/// 场景文件：相机与场景，以 JSON 格式保存
+#[derive(Serialize, Deserialize)]
+pub struct SceneFile {
+    pub version: u32, // 格式版本
+    pub camera: Camera,
+    pub scene: Scene,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation: Option<Animation>, // 关键帧动画
+}
+
+impl SceneFile {
+    pub fn new(camera: Camera, scene: Scene) -> Self {
+        Self {
+            version: SCENE_FORMAT_VERSION,
+            camera,
+            scene,
+            animation: None,
+        }
+    }
+
+    /// 写入场景文件
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.write_all(b"\n")?;
+        writer.flush()
+    }
+
+    /// 读取场景文件，拒绝比当前程序更新的格式版本；旧版本的文件读取后按当前版本保存
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut file: SceneFile = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        file.upgrade()
+            .map_err(|e| invalid_data(format!("{}: {e}", path.display())))?;
+        Ok(file)
+    }
+
+    /// 序列化为单行 JSON，用于在进程间传递场景
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// 由 JSON 读取场景，拒绝比当前程序更新的格式版本
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let mut file: SceneFile = serde_json::from_str(json)?;
+        file.upgrade().map_err(invalid_data)?;
+        Ok(file)
+    }
+
+    /// 检查格式版本，并把读入的旧版本场景标记为当前版本
+    fn upgrade(&mut self) -> Result<(), String> {
+        if self.version > SCENE_FORMAT_VERSION {
+            return Err(format!(
+                "scene format version {} is newer than supported version {}",
+                self.version, SCENE_FORMAT_VERSION
+            ));
+        }
+        self.version = SCENE_FORMAT_VERSION;
+        Ok(())
+    }
+}
+
+/// 场景中的物体：按光线类型过滤可见性，并在交点上标记光源链接
+#[derive(Serialize, Deserialize)]
+struct Tagged {
+    object: Rc<dyn Hittable>,
+    visibility: Visibility,
+    links: LightSet, // 照亮该物体的光源
+    light: LightSet, // 物体发光时所属的光源
+}
+
+impl Tagged {
+    fn tag(&self, si: &mut SurfaceInteraction) {
+        si.links = self.links;
+        si.light = self.light;
+    }
+}
+
+#[typetag::serde]
+impl Hittable for Tagged {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<SurfaceInteraction<'_>> {
+        if !self.visibility.visible_to(r.kind) {
+            return None;
+        }
+        let mut si = self.object.hit(r, ray_t)?;
+        self.tag(&mut si);
+        Some(si)
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.visibility.visible_to(r.kind) && self.object.occluded(r, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.object.bounding_box()
+    }
+
+    fn contains_instance(&self) -> bool {
+        self.object.contains_instance()
+    }
+
+    fn hit_packet<'a>(
+        &'a self,
+        packet: &mut RayPacket<PACKET_WIDTH>,
+        recs: &mut PacketRecords<'a>,
+    ) -> Mask<PACKET_WIDTH> {
+        if !self.visibility.visible_to(packet.kind) {
+            return Mask([false; PACKET_WIDTH]);
+        }
+        let hits = self.object.hit_packet(packet, recs);
+        for (rec, _) in recs.iter_mut().zip(hits.0).filter(|(_, hit)| *hit) {
+            if let Some(si) = rec {
+                self.tag(si);
+            }
+        }
+        hits
+    }
+
+    fn occluded_packet(&self, packet: &RayPacket<PACKET_WIDTH>) -> Mask<PACKET_WIDTH> {
+        if !self.visibility.visible_to(packet.kind) {
+            return Mask([false; PACKET_WIDTH]);
+        }
+        self.object.occluded_packet(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::{DiffuseLight, Lambertian},
+        sphere::Sphere,
+        vec3::{Color, Point3},
+    };
+
+    /// 一盏名为 lamp 的灯与一个只受 lights 照亮的球
+    fn scene(lights: &[&str]) -> Scene {
+        let mut scene = Scene::default();
+        let emit = Rc::new(DiffuseLight::new(Color::new(4.0, 4.0, 4.0)));
+        let gray = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        scene.add(Node::object(
+            "lamp",
+            Rc::new(Sphere::new(Point3::new(0.0, 2.0, 0.0), 0.5, emit)),
+        ));
+        scene.add(
+            Node::object(
+                "ball",
+                Rc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 0.5, gray)),
+            )
+            .with_light_links(lights),
+        );
+        scene
+    }
+
+    #[test]
+    fn linked_light_must_exist() {
+        assert!(scene(&["lamp", ENVIRONMENT_LIGHT]).build().is_ok());
+        let err = scene(&["lamp", "missing"]).build().err().unwrap();
+        assert!(err.to_string().contains("\"missing\" not found"), "{err}");
+    }
+}
@@ -0,0 +1,188 @@
+use std::ops;
+
+use crate::vec3::Real;
+
+/// 定宽数值通道，逐通道运算写成定长循环，由编译器向量化为 SIMD 指令，不依赖平台的 intrinsics
+///
+/// Vec3 的点积、叉积、最小值与最大值按 4 通道计算；需要批量计算时使用 SoA 布局的
+/// packet::Vec3Lanes
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(align(32))]
+pub struct Lanes<const N: usize>(pub [Real; N]);
+
+/// 逐通道比较结果
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mask<const N: usize>(pub [bool; N]);
+
+impl<const N: usize> Lanes<N> {
+    /// 所有通道取同一值
+    pub fn splat(value: Real) -> Self {
+        Self([value; N])
+    }
+
+    /// 逐通道应用 f
+    #[inline(always)]
+    pub fn map(self, f: impl Fn(Real) -> Real) -> Self {
+        let mut out = self.0;
+        for v in out.iter_mut() {
+            *v = f(*v);
+        }
+        Self(out)
+    }
+
+    /// 逐通道组合两组值
+    #[inline(always)]
+    pub fn zip(self, rhs: Self, f: impl Fn(Real, Real) -> Real) -> Self {
+        let mut out = self.0;
+        for (o, r) in out.iter_mut().zip(rhs.0) {
+            *o = f(*o, r);
+        }
+        Self(out)
+    }
+
+    /// 逐通道最小值
+    #[inline(always)]
+    pub fn min(self, rhs: Self) -> Self {
+        self.zip(rhs, |a, b| if a < b { a } else { b })
+    }
+
+    /// 逐通道最大值
+    #[inline(always)]
+    pub fn max(self, rhs: Self) -> Self {
+        self.zip(rhs, |a, b| if a > b { a } else { b })
+    }
+
+    /// 各通道之和
+    #[inline(always)]
+    pub fn sum(self) -> Real {
+        self.0.iter().sum()
+    }
+
+    /// 按下标重排通道
+    #[inline(always)]
+    pub fn shuffle(self, indices: [usize; N]) -> Self {
+        let mut out = self.0;
+        for (o, i) in out.iter_mut().zip(indices) {
+            *o = self.0[i];
+        }
+        Self(out)
+    }
+
+    /// 逐通道比较 self < rhs
+    #[inline(always)]
+    pub fn lt(self, rhs: Self) -> Mask<N> {
+        let mut out = [false; N];
+        for (o, (a, b)) in out.iter_mut().zip(self.0.into_iter().zip(rhs.0)) {
+            *o = a < b;
+        }
+        Mask(out)
+    }
+
+    /// 逐通道比较 self <= rhs
+    #[inline(always)]
+    pub fn le(self, rhs: Self) -> Mask<N> {
+        let mut out = [false; N];
+        for (o, (a, b)) in out.iter_mut().zip(self.0.into_iter().zip(rhs.0)) {
+            *o = a <= b;
+        }
+        Mask(out)
+    }
+
+    /// 按掩码选择：掩码为真的通道取 self，否则取 other
+    #[inline(always)]
+    pub fn select(self, mask: Mask<N>, other: Self) -> Self {
+        let mut out = other.0;
+        for (o, (m, v)) in out.iter_mut().zip(mask.0.into_iter().zip(self.0)) {
+            if m {
+                *o = v;
+            }
+        }
+        Self(out)
+    }
+}
+
+impl<const N: usize> Mask<N> {
+    /// 逐通道与
+    #[inline(always)]
+    pub fn and(self, rhs: Self) -> Self {
+        let mut out = self.0;
+        for (o, r) in out.iter_mut().zip(rhs.0) {
+            *o &= r;
+        }
+        Self(out)
+    }
+
+    /// 逐通道或
+    #[inline(always)]
+    pub fn or(self, rhs: Self) -> Self {
+        let mut out = self.0;
+        for (o, r) in out.iter_mut().zip(rhs.0) {
+            *o |= r;
+        }
+        Self(out)
+    }
+
+    /// 是否有任一通道为真
+    pub fn any(&self) -> bool {
+        self.0.iter().any(|&b| b)
+    }
+
+    /// 是否全部通道为真
+    pub fn all(&self) -> bool {
+        self.0.iter().all(|&b| b)
+    }
+
+    /// 为真的通道数
+    pub fn count(&self) -> usize {
+        self.0.iter().filter(|&&b| b).count()
+    }
+}
+
+impl<const N: usize> ops::Add for Lanes<N> {
+    type Output = Self;
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        self.zip(rhs, |a, b| a + b)
+    }
+}
+
+impl<const N: usize> ops::Sub for Lanes<N> {
+    type Output = Self;
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        self.zip(rhs, |a, b| a - b)
+    }
+}
+
+impl<const N: usize> ops::Mul for Lanes<N> {
+    type Output = Self;
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        self.zip(rhs, |a, b| a * b)
+    }
+}
+
+impl<const N: usize> ops::Div for Lanes<N> {
+    type Output = Self;
+    #[inline(always)]
+    fn div(self, rhs: Self) -> Self {
+        self.zip(rhs, |a, b| a / b)
+    }
+}
+
+impl<const N: usize> ops::Neg for Lanes<N> {
+    type Output = Self;
+    #[inline(always)]
+    fn neg(self) -> Self {
+        self.map(|a| -a)
+    }
+}
+
+impl<const N: usize> ops::Not for Mask<N> {
+    type Output = Self;
+    /// 逐通道取反
+    #[inline(always)]
+    fn not(self) -> Self {
+        Self(self.0.map(|b| !b))
+    }
+}
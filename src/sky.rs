@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    environment::Environment,
+    math::Onb,
+    rng,
+    vec3::{consts::PI, Color, Real, Vec3},
+};
+
+/// 单位辐亮度对应的亮度 (cd/m²)，与相机曝光的“晴天 16 法则”基准一致
+const LUMINANCE_UNIT: Real = 1.2 * 25600.0;
+/// 大气层外太阳表面亮度 (cd/m²)
+const SUN_LUMINANCE: Real = 2.0e9;
+/// 太阳视半径（度）
+const SUN_ANGULAR_RADIUS: Real = 0.2664;
+/// 太阳在地平线以上时，光源采样选择太阳的概率
+const SUN_SAMPLE_PROBABILITY: Real = 0.5;
+
+/// Preetham 解析天空模型，附带可采样的太阳圆盘
+///
+/// 方位角从 -z 方向起算，转向 +x 方向为正
+#[derive(Serialize, Deserialize)]
+pub struct Sky {
+    pub intensity: Real,          // 亮度倍数
+    sun_direction: Vec3,          // 指向太阳的单位向量
+    theta_sun: Real,              // 太阳天顶角
+    zenith: [Real; 3],            // 天顶处的 (Y, x, y)
+    coefficients: [[Real; 5]; 3], // Y、x、y 的 Perez 分布系数 A~E
+    sun_radiance: Color,          // 经大气衰减后的太阳辐亮度
+    cos_sun_radius: Real,         // 太阳视半径的余弦
+}
+
+impl Sky {
+    /// 由太阳高度角、方位角（度）和大气浑浊度（2 晴朗 ~ 10 雾霾）创建天空
+    pub fn new(sun_elevation: Real, sun_azimuth: Real, turbidity: Real) -> Self {
+        let (elevation, azimuth) = (sun_elevation.to_radians(), sun_azimuth.to_radians());
+        let sun_direction = Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        );
+        let theta_sun = (PI / 2.0 - elevation).clamp(0.0, PI / 2.0);
+        let t = turbidity;
+
+        // 天顶亮度 (kcd/m²) 与色度
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let zenith_y = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let (s1, s2, s3) = (theta_sun, theta_sun.powi(2), theta_sun.powi(3));
+        let zenith_cx = (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s1) * t * t
+            + (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s1 + 0.00394) * t
+            + (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s1 + 0.25886);
+        let zenith_cy = (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s1) * t * t
+            + (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s1 + 0.00516) * t
+            + (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s1 + 0.26688);
+
+        let coefficients = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        Self {
+            intensity: 1.0,
+            sun_direction,
+            theta_sun,
+            zenith: [zenith_y.max(0.0) * 1000.0, zenith_cx, zenith_cy],
+            coefficients,
+            sun_radiance: sun_radiance(elevation, t),
+            cos_sun_radius: SUN_ANGULAR_RADIUS.to_radians().cos(),
+        }
+    }
+
+    /// 由观测地纬度（度）、一年中的第几天和当地太阳时（小时）计算太阳位置并创建天空
+    pub fn at_time(latitude: Real, day_of_year: u32, solar_time: Real, turbidity: Real) -> Self {
+        let (elevation, azimuth) = sun_position(latitude, day_of_year, solar_time);
+        Self::new(elevation, azimuth, turbidity)
+    }
+
+    /// 指向太阳的单位向量
+    pub fn sun_direction(&self) -> &Vec3 {
+        &self.sun_direction
+    }
+
+    /// 太阳是否在地平线以上
+    fn sun_visible(&self) -> bool {
+        self.sun_direction.y > 0.0
+    }
+
+    /// 天空本身（不含太阳圆盘）的辐亮度
+    fn sky_radiance(&self, direction: &Vec3) -> Color {
+        let cos_theta = direction.y.max(0.01); // 地平线以下沿用地平线处的值
+        let cos_gamma = direction.dot(&self.sun_direction).clamp(-1.0, 1.0);
+        let gamma = cos_gamma.acos();
+
+        let mut values = [0.0; 3];
+        for (k, value) in values.iter_mut().enumerate() {
+            let c = &self.coefficients[k];
+            let perez = |cos_theta: Real, gamma: Real| {
+                (1.0 + c[0] * (c[1] / cos_theta).exp())
+                    * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * gamma.cos().powi(2))
+            };
+            *value = self.zenith[k] * perez(cos_theta, gamma) / perez(1.0, self.theta_sun);
+        }
+
+        xyy_to_rgb(values[0], values[1], values[2]) / LUMINANCE_UNIT
+    }
+
+    /// 在太阳圆盘内均匀采样一个方向
+    fn sample_sun(&self) -> Vec3 {
+        let cos_theta = 1.0 - rng::random::<Real>() * (1.0 - self.cos_sun_radius);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = 2.0 * PI * rng::random::<Real>();
+
+        Onb::new(&self.sun_direction).local(&Vec3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ))
+    }
+
+    /// 太阳圆盘采样的立体角概率密度
+    fn sun_pdf(&self, direction: &Vec3) -> Real {
+        if direction.dot(&self.sun_direction) >= self.cos_sun_radius {
+            1.0 / (2.0 * PI * (1.0 - self.cos_sun_radius))
+        } else {
+            0.0
+        }
+    }
+}
+
+#[typetag::serde]
+impl Environment for Sky {
+    fn radiance(&self, direction: &Vec3) -> Color {
+        let d = direction.unit_vector();
+        let mut radiance = self.sky_radiance(&d);
+        if self.sun_visible() && d.dot(&self.sun_direction) >= self.cos_sun_radius {
+            radiance += &self.sun_radiance;
+        }
+        radiance * self.intensity
+    }
+
+    fn sample(&self) -> Option<(Vec3, Real)> {
+        let direction = if self.sun_visible() && rng::random::<Real>() < SUN_SAMPLE_PROBABILITY {
+            self.sample_sun()
+        } else {
+            // 上半球均匀采样
+            let mut d = Vec3::random_unit_vector();
+            d.y = d.y.abs();
+            d
+        };
+        let pdf = self.pdf(&direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some((direction, pdf))
+    }
+
+    fn pdf(&self, direction: &Vec3) -> Real {
+        let d = direction.unit_vector();
+        let hemisphere_pdf = if d.y > 0.0 { 1.0 / (2.0 * PI) } else { 0.0 };
+        if !self.sun_visible() {
+            return hemisphere_pdf;
+        }
+        SUN_SAMPLE_PROBABILITY * self.sun_pdf(&d) + (1.0 - SUN_SAMPLE_PROBABILITY) * hemisphere_pdf
+    }
+}
+
+/// 太阳位置，返回 (高度角, 方位角)（度），方位角从正北（-z）起顺时针计算
+pub fn sun_position(latitude: Real, day_of_year: u32, solar_time: Real) -> (Real, Real) {
+    let phi = latitude.to_radians();
+    let declination =
+        (23.45 as Real).to_radians() * (2.0 * PI * (284.0 + day_of_year as Real) / 365.0).sin(); // 赤纬
+    let hour_angle = (15.0 * (solar_time - 12.0)).to_radians(); // 时角，上午为负
+
+    let sin_elevation =
+        phi.sin() * declination.sin() + phi.cos() * declination.cos() * hour_angle.cos();
+    let elevation = sin_elevation.clamp(-1.0, 1.0).asin();
+
+    let cos_azimuth = ((declination.sin() - sin_elevation * phi.sin())
+        / (elevation.cos() * phi.cos()))
+    .clamp(-1.0, 1.0);
+    let mut azimuth = cos_azimuth.acos();
+    if hour_angle > 0.0 {
+        azimuth = 2.0 * PI - azimuth; // 下午太阳位于西侧
+    }
+
+    (elevation.to_degrees(), azimuth.to_degrees())
+}
+
+/// 经大气衰减后的太阳辐亮度，按 R/G/B 代表波长计算瑞利散射与气溶胶消光
+fn sun_radiance(elevation: Real, turbidity: Real) -> Color {
+    if elevation <= 0.0 {
+        return Color::default();
+    }
+    let theta = PI / 2.0 - elevation;
+    let air_mass = 1.0 / (theta.cos() + 0.15 * (93.885 - theta.to_degrees()).powf(-1.253)); // 相对光学质量
+    let beta = 0.04608 * turbidity - 0.04586; // Ångström 浑浊度系数
+
+    let transmittance = |lambda: Real| {
+        // lambda 单位为微米
+        let rayleigh = (-0.008735 * lambda.powf(-4.08) * air_mass).exp();
+        let aerosol = (-beta * lambda.powf(-1.3) * air_mass).exp();
+        rayleigh * aerosol
+    };
+
+    Color::new(
+        transmittance(0.68),
+        transmittance(0.55),
+        transmittance(0.44),
+    ) * SUN_LUMINANCE
+        / LUMINANCE_UNIT
+}
+
+/// CIE xyY -> 线性 sRGB
+fn xyy_to_rgb(luminance: Real, x: Real, y: Real) -> Color {
+    if y <= 0.0 {
+        return Color::default();
+    }
+    let cx = x * luminance / y;
+    let cy = luminance;
+    let cz = (1.0 - x - y) * luminance / y;
+    Color::new(
+        (3.2406 * cx - 1.5372 * cy - 0.4986 * cz).max(0.0),
+        (-0.9689 * cx + 1.8758 * cy + 0.0415 * cz).max(0.0),
+        (0.0557 * cx - 0.2040 * cy + 1.0570 * cz).max(0.0),
+    )
+}
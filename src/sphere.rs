@@ -1,21 +1,30 @@
 use std::rc::Rc;
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    hittable::{HitRecord, Hittable},
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable, Shape, SurfaceInteraction},
     interval::Interval,
     material::Material,
+    math::gamma,
+    packet::{record_hits, PacketRecords, RayPacket, Vec3Lanes, PACKET_WIDTH},
     ray::Ray,
-    vec3::Vec3,
+    simd::{Lanes, Mask},
+    stats,
+    vec3::{consts::PI, Point3, Real, Vec3},
 };
 
+#[derive(Serialize, Deserialize)]
 pub struct Sphere {
-    center: Vec3,
-    radius: f64,
+    center: Point3,
+    radius: Real,
+    #[serde(with = "crate::library::reference")]
     material: Rc<dyn Material>,
 }
 
 impl Sphere {
-    pub fn new(center: Vec3, radius: f64, material: Rc<dyn Material>) -> Self {
+    pub fn new(center: Point3, radius: Real, material: Rc<dyn Material>) -> Self {
         Self {
             center,
             radius,
@@ -24,34 +33,136 @@ impl Sphere {
     }
 }
 
+#[typetag::serde]
 impl Hittable for Sphere {
-    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
-        let oc = &r.origin - &self.center; // A - C
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<SurfaceInteraction<'_>> {
+        let root = self.intersect(r, ray_t)?;
+        Some(SurfaceInteraction::new(
+            root,
+            (0.0, 0.0),
+            self.material.as_ref(),
+            self,
+        ))
+    }
 
-        let a = r.direction.length_squared();
-        let half_b = r.direction.dot(&oc);
-        let c = oc.length_squared() - self.radius * self.radius;
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.intersect(r, ray_t).is_some()
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let radius = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::from_points(&(self.center - radius), &(self.center + radius))
+    }
+
+    fn hit_packet<'a>(
+        &'a self,
+        packet: &mut RayPacket<PACKET_WIDTH>,
+        recs: &mut PacketRecords<'a>,
+    ) -> Mask<PACKET_WIDTH> {
+        let (hits, root) = self.intersect_packet(packet);
+        record_hits(packet, recs, hits, |i| {
+            SurfaceInteraction::new(root.0[i], (0.0, 0.0), self.material.as_ref(), self)
+        });
+        hits
+    }
+
+    fn occluded_packet(&self, packet: &RayPacket<PACKET_WIDTH>) -> Mask<PACKET_WIDTH> {
+        self.intersect_packet(packet).0
+    }
+}
 
-        let discriminant = half_b * half_b - a * c;
+impl Sphere {
+    /// 光线在区间 ray_t 内与球面最近交点的参数 t
+    fn intersect(&self, r: &Ray, ray_t: Interval) -> Option<Real> {
+        stats::record(|s| s.spheres.tests += 1);
+        let oc = r.origin - self.center; // A - C
+
+        // 用光线到球心的垂直距离计算判别式，并避免两根相减的抵消误差
+        // （Haines et al., Ray Tracing Gems 第 7 章）
+        let a = r.direction.length_squared();
+        let b = -r.direction.dot(&oc);
+        let l = oc + (b / a) * r.direction; // 球心到光线的垂线
+        let discriminant = self.radius * self.radius - l.length_squared();
         if discriminant < 0.0 {
-            return false;
+            return None;
         }
-        let sqrtd = discriminant.sqrt();
-
-        let mut root = (-half_b - sqrtd) / a;
-        if !ray_t.surrounds(root) {
-            root = (-half_b + sqrtd) / a;
-            if !ray_t.surrounds(root) {
-                return false;
-            }
+        let c = oc.length_squared() - self.radius * self.radius;
+        let q = b + b.signum() * (a * discriminant).sqrt();
+        let (t0, t1) = (c / q, q / a);
+        let (near, far) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+
+        let t = if ray_t.surrounds(near) {
+            near
+        } else if ray_t.surrounds(far) {
+            far
+        } else {
+            return None;
+        };
+        stats::record(|s| s.spheres.hits += 1);
+        Some(t)
+    }
+
+    /// 光线包与球面求交，返回击中的光线及各自最近交点的参数 t
+    fn intersect_packet(
+        &self,
+        packet: &RayPacket<PACKET_WIDTH>,
+    ) -> (Mask<PACKET_WIDTH>, Lanes<PACKET_WIDTH>) {
+        let oc = packet.origin - Vec3Lanes::splat(&self.center); // A - C
+
+        let r2 = Lanes::splat(self.radius * self.radius);
+        let a = packet.direction.dot(&packet.direction);
+        let b = -packet.direction.dot(&oc);
+        let k = b / a;
+        let l = Vec3Lanes {
+            x: oc.x + k * packet.direction.x,
+            y: oc.y + k * packet.direction.y,
+            z: oc.z + k * packet.direction.z,
+        };
+        let discriminant = r2 - l.dot(&l);
+        let hits = packet.active.and(Lanes::splat(0.0).le(discriminant));
+        stats::record(|s| s.spheres.tests += packet.active.count() as u64);
+        if !hits.any() {
+            return (hits, packet.t_max);
         }
+        let c = oc.dot(&oc) - r2;
+        let sqrtd = (a * discriminant).map(|d| d.max(0.0).sqrt());
+        let q = b + b.map(|b| b.signum()) * sqrtd;
+        let (t0, t1) = (c / q, q / a);
 
-        rec.t = root;
-        rec.p = r.at(rec.t);
-        let outward_normal = (&rec.p - &self.center) / self.radius; // 单位法线
-        rec.set_face_normal(r, &outward_normal); // 设置法线
-        rec.mat = self.material.clone(); // 设置材质
+        // 先取较近的根，不在区间内再取较远的根
+        let t_min = Lanes::splat(packet.t_min);
+        let near = t0.min(t1);
+        let far = t0.max(t1);
+        let near_ok = t_min.lt(near).and(near.lt(packet.t_max));
+        let far_ok = t_min.lt(far).and(far.lt(packet.t_max));
+        let hits = hits.and(near_ok.or(far_ok));
+        stats::record(|s| s.spheres.hits += hits.count() as u64);
+        (hits, near.select(near_ok, far))
+    }
+}
 
-        true
+impl Shape for Sphere {
+    fn surface<'a>(&self, r: &Ray, si: &SurfaceInteraction<'a>) -> HitRecord<'a> {
+        // 将交点重新投影到球面上，误差只来自投影本身
+        let offset = r.at(si.t) - self.center;
+        let offset = offset * (self.radius / offset.length());
+        let p = self.center + offset;
+        let p_error = gamma(5) * offset.abs() + gamma(1) * p.abs();
+        let outward_normal = offset / self.radius; // 单位法线
+        HitRecord::new(
+            r,
+            si,
+            p,
+            p_error,
+            &outward_normal,
+            sphere_uv(&outward_normal),
+        )
     }
 }
+
+/// 单位球面上点 p 的纹理坐标，u 从 -x 方向起绕 y 轴，v 从 -y 到 +y
+fn sphere_uv(p: &Vec3) -> (Real, Real) {
+    let theta = (-p.y).clamp(-1.0, 1.0).acos();
+    let phi = (-p.z).atan2(p.x) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
@@ -0,0 +1,291 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fmt::Write as _,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ray::RayKind;
+
+/// 是否收集统计，默认关闭
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 各线程合并后的统计
+static GLOBAL: Mutex<Stats> = Mutex::new(Stats::new());
+
+thread_local! {
+    /// 当前线程的统计，flush 时合并到 GLOBAL
+    static LOCAL: RefCell<Stats> = const { RefCell::new(Stats::new()) };
+}
+
+/// 开启或关闭统计收集
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// 开启统计时以 f 更新当前线程的计数
+#[inline(always)]
+pub fn record(f: impl FnOnce(&mut Stats)) {
+    if enabled() {
+        LOCAL.with_borrow_mut(f);
+    }
+}
+
+/// 把当前线程的计数合并到全局统计并清零，每个渲染线程结束工作时调用
+pub fn flush() {
+    let local = LOCAL.take();
+    GLOBAL.lock().unwrap().merge(&local);
+}
+
+/// 取出全局统计（包括当前线程尚未合并的计数）并清零
+pub fn take() -> Stats {
+    flush();
+    std::mem::take(&mut *GLOBAL.lock().unwrap())
+}
+
+/// 阶段计时器，析构时把经过的时间计入阶段 name
+pub struct PhaseTimer {
+    name: &'static str,
+    start: Instant,
+}
+
+impl PhaseTimer {
+    pub fn start(name: &'static str) -> Self {
+        Self {
+            name,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for PhaseTimer {
+    fn drop(&mut self) {
+        if enabled() {
+            let seconds = self.start.elapsed().as_secs_f64();
+            *GLOBAL
+                .lock()
+                .unwrap()
+                .phases
+                .entry(self.name.to_string())
+                .or_default() += seconds;
+        }
+    }
+}
+
+/// 各类光线的数量
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RayCounts {
+    pub camera: u64,
+    pub reflection: u64,
+    pub refraction: u64,
+    pub shadow: u64,
+}
+
+impl RayCounts {
+    pub fn add(&mut self, kind: RayKind, n: u64) {
+        match kind {
+            RayKind::Camera => self.camera += n,
+            RayKind::Reflection => self.reflection += n,
+            RayKind::Refraction => self.refraction += n,
+            RayKind::Shadow => self.shadow += n,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.camera + self.reflection + self.refraction + self.shadow
+    }
+}
+
+/// 一类图元的求交次数
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct IntersectionCounts {
+    pub tests: u64, // 求交测试次数（光线包按活跃光线计）
+    pub hits: u64,  // 击中次数
+}
+
+/// 路径结束的原因
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Terminations {
+    pub escaped: u64,   // 未击中物体
+    pub absorbed: u64,  // 材质不再散射
+    pub max_depth: u64, // 达到最大深度
+    pub roulette: u64,  // 俄罗斯轮盘赌终止
+}
+
+/// 渲染统计
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Stats {
+    pub rays: RayCounts,               // 追踪的光线数
+    pub spheres: IntersectionCounts,   // 球面求交
+    pub triangles: IntersectionCounts, // 三角形求交
+    pub bvh_node_visits: u64,          // 访问的 BVH 节点数
+    pub path_lengths: Vec<u64>,        // 路径长度（光线段数）直方图
+    pub terminations: Terminations,    // 路径结束原因
+    pub metal_absorbed: u64,           // 散射到表面以下而被吸收的金属反射光线
+    pub phases: BTreeMap<String, f64>, // 各阶段用时（秒）
+}
+
+impl Stats {
+    pub const fn new() -> Self {
+        Self {
+            rays: RayCounts {
+                camera: 0,
+                reflection: 0,
+                refraction: 0,
+                shadow: 0,
+            },
+            spheres: IntersectionCounts { tests: 0, hits: 0 },
+            triangles: IntersectionCounts { tests: 0, hits: 0 },
+            bvh_node_visits: 0,
+            path_lengths: Vec::new(),
+            terminations: Terminations {
+                escaped: 0,
+                absorbed: 0,
+                max_depth: 0,
+                roulette: 0,
+            },
+            metal_absorbed: 0,
+            phases: BTreeMap::new(),
+        }
+    }
+
+    /// 记录一条长度为 length 的路径
+    pub fn add_path(&mut self, length: usize) {
+        if self.path_lengths.len() <= length {
+            self.path_lengths.resize(length + 1, 0);
+        }
+        self.path_lengths[length] += 1;
+    }
+
+    /// 累加另一份统计
+    pub fn merge(&mut self, other: &Stats) {
+        self.rays.camera += other.rays.camera;
+        self.rays.reflection += other.rays.reflection;
+        self.rays.refraction += other.rays.refraction;
+        self.rays.shadow += other.rays.shadow;
+        for (a, b) in [
+            (&mut self.spheres, &other.spheres),
+            (&mut self.triangles, &other.triangles),
+        ] {
+            a.tests += b.tests;
+            a.hits += b.hits;
+        }
+        self.bvh_node_visits += other.bvh_node_visits;
+        for (length, &count) in other.path_lengths.iter().enumerate() {
+            if count > 0 {
+                self.path_lengths
+                    .resize(self.path_lengths.len().max(length + 1), 0);
+                self.path_lengths[length] += count;
+            }
+        }
+        self.terminations.escaped += other.terminations.escaped;
+        self.terminations.absorbed += other.terminations.absorbed;
+        self.terminations.max_depth += other.terminations.max_depth;
+        self.terminations.roulette += other.terminations.roulette;
+        self.metal_absorbed += other.metal_absorbed;
+        for (name, seconds) in other.phases.iter() {
+            *self.phases.entry(name.clone()).or_default() += seconds;
+        }
+    }
+
+    /// 平均路径长度
+    pub fn average_path_length(&self) -> f64 {
+        let (paths, segments) = self
+            .path_lengths
+            .iter()
+            .enumerate()
+            .fold((0, 0), |(paths, segments), (length, &count)| {
+                (paths + count, segments + count * length as u64)
+            });
+        if paths == 0 {
+            return 0.0;
+        }
+        segments as f64 / paths as f64
+    }
+
+    /// 渲染阶段每秒追踪的光线数
+    pub fn rays_per_second(&self) -> f64 {
+        match self.phases.get("render") {
+            Some(&seconds) if seconds > 0.0 => self.rays.total() as f64 / seconds,
+            _ => 0.0,
+        }
+    }
+
+    /// 以 JSON 输出，附带每秒光线数与平均路径长度
+    pub fn to_json(&self) -> String {
+        #[derive(Serialize)]
+        struct Report<'a> {
+            #[serde(flatten)]
+            stats: &'a Stats,
+            rays_per_second: f64,
+            average_path_length: f64,
+        }
+        let report = Report {
+            stats: self,
+            rays_per_second: self.rays_per_second(),
+            average_path_length: self.average_path_length(),
+        };
+        serde_json::to_string_pretty(&report).unwrap()
+    }
+
+    /// 便于阅读的文本报告
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        let rays = &self.rays;
+        let _ = writeln!(
+            out,
+            "Rays: {} ({:.0}/s)",
+            rays.total(),
+            self.rays_per_second()
+        );
+        let _ = writeln!(
+            out,
+            "  camera {}, reflection {}, refraction {}, shadow {}",
+            rays.camera, rays.reflection, rays.refraction, rays.shadow
+        );
+        for (name, counts) in [("Spheres", &self.spheres), ("Triangles", &self.triangles)] {
+            let rate = if counts.tests > 0 {
+                100.0 * counts.hits as f64 / counts.tests as f64
+            } else {
+                0.0
+            };
+            let _ = writeln!(
+                out,
+                "{name}: {} tests, {} hits ({rate:.1}%)",
+                counts.tests, counts.hits
+            );
+        }
+        let _ = writeln!(out, "BVH node visits: {}", self.bvh_node_visits);
+        let _ = writeln!(
+            out,
+            "Average path length: {:.2}",
+            self.average_path_length()
+        );
+        for (length, count) in self.path_lengths.iter().enumerate() {
+            if *count > 0 {
+                let _ = writeln!(out, "  {length:>3}: {count}");
+            }
+        }
+        let t = &self.terminations;
+        let _ = writeln!(
+            out,
+            "Terminations: escaped {}, absorbed {}, max depth {}, roulette {}",
+            t.escaped, t.absorbed, t.max_depth, t.roulette
+        );
+        let _ = writeln!(out, "Metal rays absorbed: {}", self.metal_absorbed);
+        for (name, seconds) in self.phases.iter() {
+            let _ = writeln!(out, "Phase {name}: {seconds:.3}s");
+        }
+        out
+    }
+}
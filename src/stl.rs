@@ -0,0 +1,96 @@
+use std::{fs, io, path::Path};
+
+use crate::{
+    image::invalid_data,
+    mesh::Mesh,
+    vec3::{Point3, Real},
+};
+
+/// 二进制 STL 的文件头长度
+const HEADER_SIZE: usize = 80;
+
+/// 二进制 STL 每个三角形的字节数：法线、三个顶点（各 3 个 f32）与 2 字节属性
+const TRIANGLE_SIZE: usize = 50;
+
+impl Mesh {
+    /// 读取 STL 网格（ASCII 或二进制）；STL 只有面法线，着色使用几何法线
+    pub fn load_stl(path: &Path) -> io::Result<Mesh> {
+        let data = fs::read(path)?;
+        let mut mesh = Mesh::default();
+        // ASCII 文件以 "solid" 开头，但部分二进制文件头也以此开头，因此先按长度判断
+        if is_binary(&data) {
+            read_binary(&data, &mut mesh);
+        } else if data.starts_with(b"solid") {
+            read_ascii(&data, &mut mesh)?;
+        } else {
+            return Err(invalid_data(format!("{}: not an STL file", path.display())));
+        }
+
+        if mesh.indices.is_empty() {
+            return Err(invalid_data(format!(
+                "{}: STL file contains no triangles",
+                path.display()
+            )));
+        }
+        Ok(mesh)
+    }
+}
+
+/// 文件长度与头部声明的三角形数一致时为二进制格式
+fn is_binary(data: &[u8]) -> bool {
+    let Some(count) = data.get(HEADER_SIZE..HEADER_SIZE + 4) else {
+        return false;
+    };
+    let count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize;
+    data.len() == HEADER_SIZE + 4 + count * TRIANGLE_SIZE
+}
+
+fn read_binary(data: &[u8], mesh: &mut Mesh) {
+    for triangle in data[HEADER_SIZE + 4..].chunks_exact(TRIANGLE_SIZE) {
+        let value = |i: usize| {
+            let b = &triangle[4 * i..4 * i + 4];
+            f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as Real
+        };
+        add_triangle(
+            mesh,
+            (1..4).map(|v| Point3::new(value(3 * v), value(3 * v + 1), value(3 * v + 2))),
+        );
+    }
+}
+
+fn read_ascii(data: &[u8], mesh: &mut Mesh) -> io::Result<()> {
+    let text = String::from_utf8_lossy(data);
+    let mut tokens = text.split_whitespace();
+    let mut vertices = Vec::with_capacity(3);
+    while let Some(token) = tokens.next() {
+        match token {
+            "vertex" => {
+                let mut coordinate = || -> io::Result<Real> {
+                    let token = tokens.next().unwrap_or_default();
+                    token
+                        .parse()
+                        .map_err(|_| invalid_data(format!("invalid STL coordinate {token:?}")))
+                };
+                vertices.push(Point3::new(coordinate()?, coordinate()?, coordinate()?));
+            }
+            "endloop" => {
+                if vertices.len() != 3 {
+                    return Err(invalid_data(format!(
+                        "STL facet with {} vertices",
+                        vertices.len()
+                    )));
+                }
+                add_triangle(mesh, vertices.drain(..));
+            }
+            _ => {} // solid、facet normal、outer loop、endfacet、endsolid
+        }
+    }
+    Ok(())
+}
+
+/// 添加一个三角形，STL 的顶点不共享
+fn add_triangle(mesh: &mut Mesh, vertices: impl Iterator<Item = Point3>) {
+    let first = mesh.positions.len();
+    mesh.positions.extend(vertices);
+    mesh.indices.push([first, first + 1, first + 2]);
+}
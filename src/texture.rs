@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hittable::HitRecord,
+    image::Image,
+    vec3::{Color, Real},
+};
+
+/// 随表面位置变化的颜色
+#[typetag::serde(tag = "type")]
+pub trait Texture: Send + Sync {
+    /// 交点 rec 处的颜色，可使用其纹理坐标、位置与顶点颜色
+    fn value(&self, rec: &HitRecord) -> Color;
+}
+
+/// 纯色纹理
+#[derive(Serialize, Deserialize)]
+pub struct SolidColor {
+    albedo: Color,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Color) -> Self {
+        Self { albedo }
+    }
+}
+
+#[typetag::serde]
+impl Texture for SolidColor {
+    fn value(&self, _rec: &HitRecord) -> Color {
+        self.albedo
+    }
+}
+
+/// 空间棋盘格纹理，按交点的世界坐标在两种纹理间交替
+#[derive(Serialize, Deserialize)]
+pub struct Checker {
+    inv_scale: Real, // 格子边长的倒数
+    #[serde(with = "crate::library::reference")]
+    even: Arc<dyn Texture>, // 偶数格
+    #[serde(with = "crate::library::reference")]
+    odd: Arc<dyn Texture>, // 奇数格
+}
+
+impl Checker {
+    pub fn new(scale: Real, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+
+    pub fn from_colors(scale: Real, even: Color, odd: Color) -> Self {
+        Self::new(
+            scale,
+            Arc::new(SolidColor::new(even)),
+            Arc::new(SolidColor::new(odd)),
+        )
+    }
+}
+
+#[typetag::serde]
+impl Texture for Checker {
+    fn value(&self, rec: &HitRecord) -> Color {
+        let p = &rec.p;
+        let x = (self.inv_scale * p.x).floor() as i64;
+        let y = (self.inv_scale * p.y).floor() as i64;
+        let z = (self.inv_scale * p.z).floor() as i64;
+        if (x + y + z) % 2 == 0 {
+            self.even.value(rec)
+        } else {
+            self.odd.value(rec)
+        }
+    }
+}
+
+/// 图像纹理，(0, 0) 对应图像左下角，超出 [0, 1] 的纹理坐标重复平铺
+#[derive(Serialize, Deserialize)]
+pub struct ImageTexture {
+    image: Arc<Image>,
+    tint: Color, // 与图像颜色相乘的系数
+}
+
+impl ImageTexture {
+    pub fn new(image: Arc<Image>) -> Self {
+        Self {
+            image,
+            tint: Color::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn with_tint(self, tint: Color) -> Self {
+        Self { tint, ..self }
+    }
+}
+
+#[typetag::serde]
+impl Texture for ImageTexture {
+    fn value(&self, rec: &HitRecord) -> Color {
+        let (u, v) = (rec.u, rec.v);
+        let (width, height) = (self.image.width, self.image.height);
+        if width == 0 || height == 0 {
+            return self.tint;
+        }
+        let u = u - u.floor();
+        let v = 1.0 - (v - v.floor()); // 图像按行自上而下存储
+        let x = ((u * width as Real) as usize).min(width - 1);
+        let y = ((v * height as Real) as usize).min(height - 1);
+        self.image.get(x, y) * self.tint
+    }
+}
+
+/// 顶点颜色纹理，取网格在交点处插值得到的顶点颜色，没有顶点颜色的物体为白色
+#[derive(Serialize, Deserialize)]
+pub struct VertexColor;
+
+#[typetag::serde]
+impl Texture for VertexColor {
+    fn value(&self, rec: &HitRecord) -> Color {
+        rec.color
+    }
+}
@@ -0,0 +1,162 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable, Shape, SurfaceInteraction},
+    interval::Interval,
+    material::Material,
+    math::gamma,
+    packet::{record_hits, PacketRecords, RayPacket, Vec3Lanes, PACKET_WIDTH},
+    ray::Ray,
+    simd::{Lanes, Mask},
+    stats,
+    vec3::{Point3, Real, Vec3},
+};
+
+/// 行列式绝对值小于该值时，认为光线与三角形平行
+const PARALLEL_EPSILON: Real = 1e-8;
+
+/// 光线包求交结果：击中的光线及各自的 t 与重心坐标 u、v
+pub(crate) type PacketIntersection = (
+    Mask<PACKET_WIDTH>,
+    Lanes<PACKET_WIDTH>,
+    Lanes<PACKET_WIDTH>,
+    Lanes<PACKET_WIDTH>,
+);
+
+/// 三角形，外法线方向由顶点的逆时针顺序决定
+#[derive(Serialize, Deserialize)]
+pub struct Triangle {
+    v0: Point3,
+    e1: Vec3,                // v1 - v0
+    e2: Vec3,                // v2 - v0
+    pub(crate) normal: Vec3, // 单位外法线
+    #[serde(with = "crate::library::reference")]
+    pub(crate) material: Rc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(v0: Point3, v1: Point3, v2: Point3, material: Rc<dyn Material>) -> Self {
+        let e1 = v1 - v0;
+        let e2 = v2 - v0;
+        Self {
+            v0,
+            e1,
+            e2,
+            normal: e1.cross(&e2).unit_vector(),
+            material,
+        }
+    }
+
+    /// Möller–Trumbore 求交，返回区间 ray_t 内交点的 (t, u, v)，(u, v) 为重心坐标
+    pub(crate) fn intersect(&self, r: &Ray, ray_t: Interval) -> Option<(Real, Real, Real)> {
+        stats::record(|s| s.triangles.tests += 1);
+        let pvec = r.direction.cross(&self.e2);
+        let det = self.e1.dot(&pvec);
+        if det.abs() < PARALLEL_EPSILON {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        let tvec = r.origin - self.v0;
+        let u = tvec.dot(&pvec) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let qvec = tvec.cross(&self.e1);
+        let v = r.direction.dot(&qvec) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = self.e2.dot(&qvec) * inv_det;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+        stats::record(|s| s.triangles.hits += 1);
+        Some((t, u, v))
+    }
+
+    /// 光线包的 Möller–Trumbore 求交，返回击中的光线及各自的 t、u、v
+    pub(crate) fn intersect_packet(&self, packet: &RayPacket<PACKET_WIDTH>) -> PacketIntersection {
+        let e1 = Vec3Lanes::splat(&self.e1);
+        let e2 = Vec3Lanes::splat(&self.e2);
+        let zero = Lanes::splat(0.0);
+        let one = Lanes::splat(1.0);
+
+        let pvec = packet.direction.cross(&e2);
+        let det = e1.dot(&pvec);
+        let parallel = det.map(|d| d.abs()).lt(Lanes::splat(PARALLEL_EPSILON));
+        let inv_det = one / det;
+
+        let tvec = packet.origin - Vec3Lanes::splat(&self.v0);
+        let u = tvec.dot(&pvec) * inv_det;
+        let qvec = tvec.cross(&e1);
+        let v = packet.direction.dot(&qvec) * inv_det;
+        let t = e2.dot(&qvec) * inv_det;
+
+        let hits = packet
+            .active
+            .and(!parallel)
+            .and(zero.le(u))
+            .and(zero.le(v))
+            .and((u + v).le(one))
+            .and(Lanes::splat(packet.t_min).lt(t))
+            .and(t.lt(packet.t_max));
+        stats::record(|s| {
+            s.triangles.tests += packet.active.count() as u64;
+            s.triangles.hits += hits.count() as u64;
+        });
+        (hits, t, u, v)
+    }
+}
+
+impl Shape for Triangle {
+    fn surface<'a>(&self, r: &Ray, si: &SurfaceInteraction<'a>) -> HitRecord<'a> {
+        // 由重心坐标插值交点，比 r.at(t) 的误差更小且与 t 无关
+        let (u, v) = si.uv;
+        let (b1, b2) = (u * self.e1, v * self.e2);
+        let p = self.v0 + b1 + b2;
+        let p_error = gamma(7) * (self.v0.abs() + b1.abs() + b2.abs());
+        HitRecord::new(r, si, p, p_error, &self.normal, si.uv)
+    }
+}
+
+#[typetag::serde]
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<SurfaceInteraction<'_>> {
+        let (t, u, v) = self.intersect(r, ray_t)?;
+        Some(SurfaceInteraction::new(
+            t,
+            (u, v),
+            self.material.as_ref(),
+            self,
+        ))
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: Interval) -> bool {
+        self.intersect(r, ray_t).is_some()
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_points(&self.v0, &(self.v0 + self.e1)).union_point(&(self.v0 + self.e2))
+    }
+
+    fn hit_packet<'a>(
+        &'a self,
+        packet: &mut RayPacket<PACKET_WIDTH>,
+        recs: &mut PacketRecords<'a>,
+    ) -> Mask<PACKET_WIDTH> {
+        let (hits, t, u, v) = self.intersect_packet(packet);
+        record_hits(packet, recs, hits, |i| {
+            SurfaceInteraction::new(t.0[i], (u.0[i], v.0[i]), self.material.as_ref(), self)
+        });
+        hits
+    }
+
+    fn occluded_packet(&self, packet: &RayPacket<PACKET_WIDTH>) -> Mask<PACKET_WIDTH> {
+        self.intersect_packet(packet).0
+    }
+}
@@ -1,73 +1,122 @@
 use overload::overload;
-use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::ops;
 
-// 三维向量
-#[derive(Clone, Default)]
+use crate::{rng, simd::Lanes};
+
+/// 浮点标量类型，启用 `f32` 特性时为单精度
+#[cfg(not(feature = "f32"))]
+pub type Real = f64;
+#[cfg(feature = "f32")]
+pub type Real = f32;
+
+// 与 Real 对应的数学常量
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+
+// 三维向量，序列化为 [x, y, z]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "[Real; 3]", into = "[Real; 3]")]
 pub struct Vec3 {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
+    pub x: Real,
+    pub y: Real,
+    pub z: Real,
 }
 
 impl Vec3 {
     /// 创建三维向量
-    pub fn new(x: f64, y: f64, z: f64) -> Self {
+    pub fn new(x: Real, y: Real, z: Real) -> Self {
         Vec3 { x, y, z }
     }
 
+    /// 转换为 4 通道（第 4 通道为 0）
+    #[inline(always)]
+    fn lanes(&self) -> Lanes<4> {
+        Lanes([self.x, self.y, self.z, 0.0])
+    }
+
+    #[inline(always)]
+    fn from_lanes(lanes: Lanes<4>) -> Self {
+        Vec3::new(lanes.0[0], lanes.0[1], lanes.0[2])
+    }
+
     /// 长度平方
-    pub fn length_squared(&self) -> f64 {
-        self.x * self.x + self.y * self.y + self.z * self.z
+    pub fn length_squared(&self) -> Real {
+        self.dot(self)
     }
 
     /// 长度
-    pub fn length(&self) -> f64 {
+    pub fn length(&self) -> Real {
         self.length_squared().sqrt()
     }
 
     /// 点积
-    pub fn dot(&self, rhs: &Vec3) -> f64 {
-        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    #[inline]
+    pub fn dot(&self, rhs: &Vec3) -> Real {
+        (self.lanes() * rhs.lanes()).sum()
     }
 
-    /// 叉积
+    /// 叉积：a.yzx * b.zxy - a.zxy * b.yzx
+    #[inline]
     pub fn cross(&self, rhs: &Vec3) -> Self {
-        Vec3 {
-            x: self.y * rhs.z - self.z * rhs.y,
-            y: self.z * rhs.x - self.x * rhs.z,
-            z: self.x * rhs.y - self.y * rhs.x,
-        }
+        const YZX: [usize; 4] = [1, 2, 0, 3];
+        const ZXY: [usize; 4] = [2, 0, 1, 3];
+        let (a, b) = (self.lanes(), rhs.lanes());
+        Self::from_lanes(a.shuffle(YZX) * b.shuffle(ZXY) - a.shuffle(ZXY) * b.shuffle(YZX))
+    }
+
+    /// 逐分量最小值
+    #[inline]
+    pub fn min(&self, rhs: &Vec3) -> Self {
+        Self::from_lanes(self.lanes().min(rhs.lanes()))
+    }
+
+    /// 逐分量最大值
+    #[inline]
+    pub fn max(&self, rhs: &Vec3) -> Self {
+        Self::from_lanes(self.lanes().max(rhs.lanes()))
+    }
+
+    /// 逐分量绝对值
+    pub fn abs(&self) -> Self {
+        Vec3::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
+
+    /// 亮度（视为线性 RGB 颜色）
+    pub fn luminance(&self) -> Real {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
     }
 
     /// 单位向量
     pub fn unit_vector(&self) -> Self {
-        self.clone() / self.length()
+        *self / self.length()
     }
 
     /// 随机向量
     pub fn random() -> Vec3 {
         Vec3 {
-            x: rand::random::<f64>(),
-            y: rand::random::<f64>(),
-            z: rand::random::<f64>(),
+            x: rng::random::<Real>(),
+            y: rng::random::<Real>(),
+            z: rng::random::<Real>(),
         }
     }
 
     /// 有范围的随机向量
-    pub fn random_in_range(min: f64, max: f64) -> Vec3 {
+    pub fn random_in_range(min: Real, max: Real) -> Vec3 {
         Vec3 {
-            x: min + (max - min) * rand::random::<f64>(),
-            y: min + (max - min) * rand::random::<f64>(),
-            z: min + (max - min) * rand::random::<f64>(),
+            x: min + (max - min) * rng::random::<Real>(),
+            y: min + (max - min) * rng::random::<Real>(),
+            z: min + (max - min) * rng::random::<Real>(),
         }
     }
 
     pub fn random_in_unit_disk() -> Vec3 {
         loop {
             let p = Vec3 {
-                x: rand::thread_rng().gen_range(-1.0..1.0),
-                y: rand::thread_rng().gen_range(-1.0..1.0),
+                x: rng::gen_range(-1.0..1.0),
+                y: rng::gen_range(-1.0..1.0),
                 z: 0.0,
             };
             if p.length_squared() < 1.0 {
@@ -105,7 +154,7 @@ impl Vec3 {
 
     /// 判断是否接近0
     pub fn near_zero(&self) -> bool {
-        const S: f64 = 1e-8;
+        const S: Real = 1e-8;
         (self.x.abs() < S) && (self.y.abs() < S) && (self.z.abs() < S)
     }
 
@@ -114,7 +163,7 @@ impl Vec3 {
     }
 
     /// 折射
-    pub fn refract(uv: &Vec3, n: &Vec3, etai_over_etat: f64) -> Vec3 {
+    pub fn refract(uv: &Vec3, n: &Vec3, etai_over_etat: Real) -> Vec3 {
         let cos_theta = (-uv).dot(n).min(1.0); // 取最小值
         let r_out_perp = etai_over_etat * (uv + cos_theta * n); // 法线方向
         let r_out_parallel = -(1.0 - r_out_perp.length_squared()).sqrt() * n; // 平行方向
@@ -122,6 +171,31 @@ impl Vec3 {
     }
 }
 
+impl ops::Index<usize> for Vec3 {
+    type Output = Real;
+    /// 按坐标轴取分量（0: x, 1: y, 2: z），其他下标 panic
+    fn index(&self, axis: usize) -> &Real {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("index out of range: the len is 3 but the index is {axis}"),
+        }
+    }
+}
+
+impl From<[Real; 3]> for Vec3 {
+    fn from([x, y, z]: [Real; 3]) -> Self {
+        Vec3 { x, y, z }
+    }
+}
+
+impl From<Vec3> for [Real; 3] {
+    fn from(v: Vec3) -> Self {
+        [v.x, v.y, v.z]
+    }
+}
+
 // Add
 overload!((a: ?Vec3) + (b: ?Vec3) -> Vec3 {
     Vec3 {
@@ -149,7 +223,7 @@ overload!((a: ?Vec3) * (b: ?Vec3) -> Vec3 {
     }
 });
 
-overload!((a: ?Vec3) * (b: ?f64) -> Vec3 {
+overload!((a: ?Vec3) * (b: ?Real) -> Vec3 {
     Vec3 {
         x: a.x * b,
         y: a.y * b,
@@ -157,12 +231,12 @@ overload!((a: ?Vec3) * (b: ?f64) -> Vec3 {
     }
 });
 
-overload!((a: ?f64) * (b: ?Vec3) -> Vec3 {
+overload!((a: ?Real) * (b: ?Vec3) -> Vec3 {
     b * a
 });
 
 // Div
-overload!((a: ?Vec3) / (b: ?f64) -> Vec3 {
+overload!((a: ?Vec3) / (b: ?Real) -> Vec3 {
     Vec3 {
         x: a.x / b,
         y: a.y / b,
@@ -185,3 +259,100 @@ overload!((a: &mut Vec3) += (b: ?Vec3) {
     a.y += b.y;
     a.z += b.z;
 });
+
+/// 为包装 Vec3 的新类型实现构造、解引用与相互转换
+macro_rules! vec3_newtype {
+    ($name:ident) => {
+        impl $name {
+            pub fn new(x: Real, y: Real, z: Real) -> Self {
+                Self(Vec3::new(x, y, z))
+            }
+        }
+
+        impl ops::Deref for $name {
+            type Target = Vec3;
+            fn deref(&self) -> &Vec3 {
+                &self.0
+            }
+        }
+
+        impl From<Vec3> for $name {
+            fn from(v: Vec3) -> Self {
+                Self(v)
+            }
+        }
+
+        impl From<$name> for Vec3 {
+            fn from(v: $name) -> Vec3 {
+                v.0
+            }
+        }
+    };
+}
+
+/// 空间中的点
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Point3(pub Vec3);
+
+/// 线性 RGB 颜色
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Color(pub Vec3);
+
+/// 表面法线（单位向量）
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Normal3(pub Vec3);
+
+vec3_newtype!(Point3);
+vec3_newtype!(Color);
+vec3_newtype!(Normal3);
+
+// 点 - 点 = 向量，点 ± 向量 = 点
+overload!((a: ?Point3) - (b: ?Point3) -> Vec3 { a.0 - b.0 });
+overload!((a: ?Point3) + (b: ?Vec3) -> Point3 { Point3(a.0 + b) });
+overload!((a: ?Point3) - (b: ?Vec3) -> Point3 { Point3(a.0 - b) });
+overload!((a: &mut Point3) += (b: ?Vec3) { a.0 += b; });
+
+// 颜色按分量运算
+overload!((a: ?Color) + (b: ?Color) -> Color { Color(a.0 + b.0) });
+overload!((a: ?Color) * (b: ?Color) -> Color { Color(a.0 * b.0) });
+overload!((a: ?Color) * (b: ?Real) -> Color { Color(a.0 * b) });
+overload!((a: ?Real) * (b: ?Color) -> Color { Color(b.0 * a) });
+overload!((a: ?Color) / (b: ?Real) -> Color { Color(a.0 / b) });
+overload!((a: &mut Color) += (b: ?Color) { a.0 += b.0; });
+
+// 法线取反仍为法线
+overload!(- (a: ?Normal3) -> Normal3 { Normal3(-a.0) });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lane_kernels_match_component_formulas() {
+        let a = Vec3::new(1.5, -2.0, 0.25);
+        let b = Vec3::new(-3.0, 0.5, 4.0);
+        assert_eq!(a.dot(&b), a.x * b.x + a.y * b.y + a.z * b.z);
+        assert_eq!(
+            a.cross(&b),
+            Vec3::new(
+                a.y * b.z - a.z * b.y,
+                a.z * b.x - a.x * b.z,
+                a.x * b.y - a.y * b.x
+            )
+        );
+        assert_eq!(a.min(&b), Vec3::new(-3.0, -2.0, 0.25));
+        assert_eq!(a.max(&b), Vec3::new(1.5, 0.5, 4.0));
+    }
+
+    #[test]
+    fn index_selects_components() {
+        let v = Vec3::new(1.0, 2.0, 3.0);
+        assert_eq!([v[0], v[1], v[2]], [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of range")]
+    fn index_past_z_panics() {
+        let _ = Vec3::new(1.0, 2.0, 3.0)[3];
+    }
+}
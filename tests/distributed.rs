@@ -0,0 +1,108 @@
+use std::{
+    net::TcpListener,
+    process::{Child, Command, Stdio},
+    rc::Rc,
+};
+
+use ray_tracing::{
+    distributed, Camera, CancelToken, Color, Dielectric, Image, Lambertian, Metal, Node, Point3,
+    Scene, SceneFile, Sphere,
+};
+
+/// 小场景：漫反射、金属与玻璃球放在地面上
+fn scene_file() -> SceneFile {
+    let mut scene = Scene::default();
+    let ground = scene.materials.add(
+        "ground",
+        Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+    );
+    let diffuse = scene.materials.add(
+        "diffuse",
+        Rc::new(Lambertian::new(Color::new(0.7, 0.3, 0.2))),
+    );
+    let metal = scene
+        .materials
+        .add("metal", Rc::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.1)));
+    let glass = scene.materials.add("glass", Rc::new(Dielectric::new(1.5)));
+    scene.add(Node::object(
+        "ground",
+        Rc::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, ground)),
+    ));
+    for (name, x, material) in [
+        ("left", -1.0, diffuse),
+        ("center", 0.0, metal),
+        ("right", 1.0, glass),
+    ] {
+        scene.add(Node::object(
+            name,
+            Rc::new(Sphere::new(Point3::new(x, 0.0, -1.0), 0.5, material)),
+        ));
+    }
+
+    let mut camera = Camera::default();
+    camera.image_width = 48;
+    camera.aspect_ratio = 4.0 / 3.0;
+    camera.samples_per_pixel = 6;
+    camera.max_depth = 8;
+    camera.lookfrom = Point3::new(0.0, 0.5, 1.5);
+    camera.lookat = Point3::new(0.0, 0.0, -1.0);
+    camera.seed = 7;
+    SceneFile::new(camera, scene)
+}
+
+/// 测试结束（包括失败）时结束仍在运行的工作进程
+struct Workers(Vec<Child>);
+
+impl Drop for Workers {
+    fn drop(&mut self) {
+        for child in self.0.iter_mut() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// 在本机空闲端口上启动 workers 个 `ray-tracing --worker` 进程分布式渲染，
+/// 每项任务 samples_per_job 个采样
+fn render(workers: usize, samples_per_job: usize) -> Image {
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .unwrap();
+    let mut children = Workers(
+        (0..workers)
+            .map(|_| {
+                Command::new(env!("CARGO_BIN_EXE_ray-tracing"))
+                    .arg("--worker")
+                    .arg(addr.to_string())
+                    .stderr(Stdio::null())
+                    .spawn()
+                    .unwrap()
+            })
+            .collect(),
+    );
+    let image =
+        distributed::coordinate(&mut scene_file(), addr, samples_per_job, &mut |_| {}).unwrap();
+    for child in children.0.iter_mut() {
+        assert!(child.wait().unwrap().success());
+    }
+    image
+}
+
+#[test]
+fn worker_count_does_not_change_the_image() {
+    let one = render(1, 2);
+    let two = render(2, 2);
+    assert_eq!((one.width, one.height), (two.width, two.height));
+    assert!(one.pixels.iter().any(|p| p.x > 0.0));
+    assert!(one.pixels == two.pixels);
+}
+
+#[test]
+fn workers_match_a_local_render() {
+    let SceneFile {
+        mut camera, scene, ..
+    } = scene_file();
+    let local = camera.render_image(&scene.build().unwrap(), &mut |_| {}, &CancelToken::new());
+    let distributed = render(3, 0);
+    assert!(local.pixels == distributed.pixels);
+}